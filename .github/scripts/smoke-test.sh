@@ -11,7 +11,9 @@ PORT=9877
 DB=regan-ci.db
 rm -f "$DB"
 
-"$SERVER" --port "$PORT" --image-dir "$IMG_DIR" --output "sqlite://$DB" &
+CACHE_DIR=$(mktemp -d)
+
+"$SERVER" --port "$PORT" --image-dir "$IMG_DIR" --cache-dir "$CACHE_DIR" --output "sqlite://$DB" &
 SERVER_PID=$!
 
 for i in $(seq 1 30); do
@@ -26,6 +28,14 @@ echo "=== GET / ==="
 curl -sSf "http://localhost:$PORT/" > /dev/null
 echo "OK"
 
+echo "=== GET /admin ==="
+curl -sSf "http://localhost:$PORT/admin" > /dev/null
+echo "OK"
+
+echo "=== GET /leaderboard ==="
+curl -sSf "http://localhost:$PORT/leaderboard" > /dev/null
+echo "OK"
+
 echo "=== GET /style.css ==="
 curl -sSf "http://localhost:$PORT/style.css" > /dev/null
 echo "OK"
@@ -34,7 +44,31 @@ echo "=== GET /matches ==="
 curl -sSf "http://localhost:$PORT/matches" > /dev/null
 echo "OK"
 
+echo "=== GET /status ==="
+curl -sSf "http://localhost:$PORT/status" > /dev/null
+echo "OK"
+
+echo "=== GET /stats ==="
+curl -sSf "http://localhost:$PORT/stats" > /dev/null
+echo "OK"
+
+echo "=== GET /api/keybindings ==="
+curl -sSf "http://localhost:$PORT/api/keybindings" > /dev/null
+echo "OK"
+
+echo "=== GET /api/settings ==="
+curl -sSf "http://localhost:$PORT/api/settings" > /dev/null
+echo "OK"
+
+echo "=== GET /api/session ==="
+curl -sSf "http://localhost:$PORT/api/session?annotator=smoke" > /dev/null
+echo "OK"
+
+echo "=== GET /images/1.png?w=64 ==="
+curl -sSf "http://localhost:$PORT/images/1.png?w=64" > /dev/null
+echo "OK"
+
 kill "$SERVER_PID" 2>/dev/null || true
 wait "$SERVER_PID" 2>/dev/null || true
 rm -f "$DB"
-rm -rf "$IMG_DIR"
+rm -rf "$IMG_DIR" "$CACHE_DIR"
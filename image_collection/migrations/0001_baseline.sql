@@ -15,4 +15,3 @@ CREATE TABLE IF NOT EXISTS matches(
 
 CREATE INDEX IF NOT EXISTS idx_player_rating ON players (rating); 
 CREATE INDEX IF NOT EXISTS idx_player_deviation ON players(deviation);
-
@@ -0,0 +1 @@
+ALTER TABLE matches ADD COLUMN response_ms INTEGER CHECK (response_ms >= 0);
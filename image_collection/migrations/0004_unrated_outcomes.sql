@@ -0,0 +1,23 @@
+-- graded results and matches without a result, like skipped pairs.
+-- Constraints can not be altered in sqlite, so the table gets rebuilt.
+CREATE TABLE matches_upgraded(
+    id INTEGER PRIMARY KEY NOT NULL,
+    home_players_id INTEGER NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    guest_players_id INTEGER NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    result REAL CHECK (result IN (0.0, 0.25, 0.5, 0.75, 1.0)),
+    timestamp TEXT NOT NULL,
+    home_side TEXT CHECK (home_side IN ('left', 'right')),
+    response_ms INTEGER CHECK (response_ms >= 0),
+    outcome TEXT CHECK (outcome IN ('skip', 'flag_home', 'flag_guest')),
+    CHECK ((result IS NULL) != (outcome IS NULL))
+);
+
+INSERT INTO matches_upgraded
+    (id, home_players_id, guest_players_id, result, timestamp, home_side, response_ms)
+    SELECT id, home_players_id, guest_players_id, result, timestamp, home_side, response_ms
+    FROM matches;
+
+DROP TABLE matches;
+ALTER TABLE matches_upgraded RENAME TO matches;
+
+ALTER TABLE players ADD COLUMN flagged BOOLEAN NOT NULL DEFAULT FALSE;
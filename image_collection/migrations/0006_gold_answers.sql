@@ -0,0 +1,16 @@
+ALTER TABLE matches ADD COLUMN annotator TEXT;
+
+CREATE TABLE IF NOT EXISTS gold_answers(
+    id INTEGER PRIMARY KEY NOT NULL,
+    home_players_id INTEGER NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    guest_players_id INTEGER NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    expected REAL NOT NULL,
+    result REAL NOT NULL,
+    correct BOOLEAN NOT NULL,
+    annotator TEXT,
+    timestamp TEXT NOT NULL,
+    response_ms INTEGER CHECK (response_ms >= 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_matches_annotator ON matches(annotator);
+CREATE INDEX IF NOT EXISTS idx_gold_answers_annotator ON gold_answers(annotator);
@@ -0,0 +1,12 @@
+-- times of the sessions are unix seconds
+CREATE TABLE IF NOT EXISTS sessions(
+    id INTEGER PRIMARY KEY NOT NULL,
+    annotator TEXT NOT NULL,
+    started INTEGER NOT NULL,
+    last_active INTEGER NOT NULL,
+    matches INTEGER NOT NULL DEFAULT 0
+);
+
+ALTER TABLE matches ADD COLUMN session_id INTEGER REFERENCES sessions(id);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_annotator ON sessions(annotator);
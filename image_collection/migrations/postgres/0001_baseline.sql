@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS players (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL,
+    rating DOUBLE PRECISION NOT NULL,
+    deviation DOUBLE PRECISION NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS matches(
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    home_players_id BIGINT NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    guest_players_id BIGINT NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    result DOUBLE PRECISION NOT NULL CHECK (result IN (0.0, 0.5, 1.0)),
+    timestamp TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_player_rating ON players(rating);
+CREATE INDEX IF NOT EXISTS idx_player_deviation ON players(deviation);
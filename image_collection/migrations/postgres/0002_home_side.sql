@@ -0,0 +1 @@
+ALTER TABLE matches ADD COLUMN home_side TEXT CHECK (home_side IN ('left', 'right'));
@@ -0,0 +1 @@
+ALTER TABLE matches ADD COLUMN response_ms BIGINT CHECK (response_ms >= 0);
@@ -0,0 +1,8 @@
+-- graded results and matches without a result, like skipped pairs
+ALTER TABLE matches DROP CONSTRAINT matches_result_check;
+ALTER TABLE matches ALTER COLUMN result DROP NOT NULL;
+ALTER TABLE matches ADD CONSTRAINT matches_result_check CHECK (result IN (0.0, 0.25, 0.5, 0.75, 1.0));
+ALTER TABLE matches ADD COLUMN outcome TEXT CHECK (outcome IN ('skip', 'flag_home', 'flag_guest'));
+ALTER TABLE matches ADD CHECK ((result IS NULL) != (outcome IS NULL));
+
+ALTER TABLE players ADD COLUMN flagged BOOLEAN NOT NULL DEFAULT FALSE;
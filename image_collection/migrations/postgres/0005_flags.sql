@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS flags(
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    players_id BIGINT NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL CHECK (reason IN ('corrupt', 'mislabeled', 'out_of_scope', 'other')),
+    comment TEXT,
+    timestamp TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'cleared', 'removed'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_flags_players ON flags(players_id);
@@ -0,0 +1,16 @@
+ALTER TABLE matches ADD COLUMN annotator TEXT;
+
+CREATE TABLE IF NOT EXISTS gold_answers(
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    home_players_id BIGINT NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    guest_players_id BIGINT NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+    expected DOUBLE PRECISION NOT NULL,
+    result DOUBLE PRECISION NOT NULL,
+    correct BOOLEAN NOT NULL,
+    annotator TEXT,
+    timestamp TEXT NOT NULL,
+    response_ms BIGINT CHECK (response_ms >= 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_matches_annotator ON matches(annotator);
+CREATE INDEX IF NOT EXISTS idx_gold_answers_annotator ON gold_answers(annotator);
@@ -0,0 +1,12 @@
+-- times of the sessions are unix seconds
+CREATE TABLE IF NOT EXISTS sessions(
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    annotator TEXT NOT NULL,
+    started BIGINT NOT NULL,
+    last_active BIGINT NOT NULL,
+    matches BIGINT NOT NULL DEFAULT 0
+);
+
+ALTER TABLE matches ADD COLUMN session_id BIGINT REFERENCES sessions(id);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_annotator ON sessions(annotator);
@@ -0,0 +1,5 @@
+-- annotators without an entry have a weight of 1
+CREATE TABLE IF NOT EXISTS annotators(
+    name TEXT PRIMARY KEY NOT NULL,
+    weight DOUBLE PRECISION NOT NULL CHECK (weight >= 0)
+);
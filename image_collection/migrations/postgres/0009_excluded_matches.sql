@@ -0,0 +1,2 @@
+-- judgements of excluded annotators are kept, but do not count as games
+ALTER TABLE matches ADD COLUMN excluded BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,270 @@
+//! Snapshots of the database with `VACUUM INTO`.
+//!
+//! The snapshot is read in a single read transaction, so it is consistent and does not block
+//! the annotators, who keep judging in the WAL. It gets written under a temporary name first,
+//! so an interrupted backup never looks like a complete one.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tracing::*;
+
+use crate::storage::Db;
+
+const PREFIX: &str = "backup-";
+const EXTENSION: &str = ".db";
+
+/// Where the snapshots of the database are written
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackupOptions {
+    pub dir: PathBuf,
+    /// number of snapshots which are kept, older ones get deleted
+    pub keep: usize,
+}
+
+/// A written snapshot
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Backup {
+    pub path: String,
+    pub bytes: u64,
+    /// snapshots deleted by the rotation
+    pub removed: Vec<String>,
+}
+
+/// file of a snapshot in progress, deleted unless the snapshot was completed
+struct Partial(PathBuf);
+
+impl Drop for Partial {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+pub(crate) async fn backup(db: &Db, options: &BackupOptions) -> Result<Backup> {
+    let db = match db {
+        Db::Sqlite(db) => db,
+        Db::Postgres(_) => bail!("backups of PostgreSQL databases are not supported, use pg_dump"),
+    };
+    std::fs::create_dir_all(&options.dir)
+        .with_context(|| format!("cannot create backup directory {}", options.dir.display()))?;
+    let path = options
+        .dir
+        .join(format!("{PREFIX}{}{EXTENSION}", timestamp(db).await?));
+    if path.exists() {
+        bail!("backup {} already exists", path.display());
+    }
+    let partial = Partial(path.with_extension("db.partial"));
+    let target = target_uri(
+        partial
+            .0
+            .to_str()
+            .ok_or_else(|| anyhow!("backup path {} is not valid utf-8", partial.0.display()))?,
+    );
+
+    let now = std::time::Instant::now();
+    // VACUUM INTO needs a new file
+    let _ = std::fs::remove_file(&partial.0);
+    sqlx::query!("VACUUM INTO ?", target).execute(db).await?;
+    std::fs::rename(&partial.0, &path)?;
+    let bytes = std::fs::metadata(&path)?.len();
+    info!(
+        "Backup {} written in {}ms.",
+        path.display(),
+        now.elapsed().as_millis()
+    );
+    let removed = rotate(&options.dir, options.keep)?;
+    Ok(Backup {
+        path: path.to_string_lossy().to_string(),
+        bytes,
+        removed,
+    })
+}
+
+/// the target of VACUUM INTO as uri. It is opened like the database, so a plain path of an
+/// in-memory database would end up in memory as well.
+fn target_uri(path: &str) -> String {
+    let path = path
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23");
+    format!("file:{path}?mode=rwc")
+}
+
+/// UTC time with milliseconds, which sorts like the time and is valid in file names
+async fn timestamp(db: &SqlitePool) -> Result<String> {
+    let time =
+        sqlx::query_scalar!(r#"SELECT strftime('%Y-%m-%dT%H-%M-%f', 'now') as "time!: String""#)
+            .fetch_one(db)
+            .await?;
+    Ok(time)
+}
+
+/// deletes the oldest snapshots in `dir`, so at most `keep` remain
+fn rotate(dir: &Path, keep: usize) -> Result<Vec<String>> {
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if name.starts_with(PREFIX) && name.ends_with(EXTENSION) {
+            backups.push(name);
+        }
+    }
+    backups.sort();
+    let outdated = backups.len().saturating_sub(std::cmp::max(1, keep));
+    let mut removed = Vec::new();
+    for name in backups.into_iter().take(outdated) {
+        let path = dir.join(&name);
+        std::fs::remove_file(&path)?;
+        info!("Removed old backup {}.", path.display());
+        removed.push(path.to_string_lossy().to_string());
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ImageCollection, ImageCollectionOptions, Match, MatchOutcome, RatingModel, Storage,
+    };
+
+    /// judges while the backup is written and checks the snapshot
+    async fn backup_while_judging(ic: &ImageCollection, dir: &Path) {
+        let options = BackupOptions {
+            dir: dir.to_path_buf(),
+            keep: 1,
+        };
+        let judging = async {
+            for i in 0..20 {
+                let m = Match {
+                    home_id: 1 + i % 4,
+                    guest_id: 5,
+                    won: MatchOutcome::HomeWin,
+                    home_side: None,
+                    response_ms: None,
+                    annotator: None,
+                    session_id: None,
+                };
+                ic.db
+                    .update_rating(&m, &RatingModel::default())
+                    .await
+                    .unwrap();
+                tokio::task::yield_now().await;
+            }
+        };
+        let (written, ()) = tokio::join!(backup(&ic.reader, &options), judging);
+        let written = written.unwrap();
+
+        let snapshot = sqlx::sqlite::SqlitePoolOptions::new()
+            .connect_with(
+                sqlx::sqlite::SqliteConnectOptions::new()
+                    .filename(&written.path)
+                    .read_only(true),
+            )
+            .await
+            .unwrap();
+        let integrity: String = sqlx::query_scalar("PRAGMA integrity_check")
+            .fetch_one(&snapshot)
+            .await
+            .unwrap();
+        assert_eq!(integrity, "ok");
+        let matches: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM matches")
+            .fetch_one(&snapshot)
+            .await
+            .unwrap();
+        let players = Db::Sqlite(snapshot.clone()).players().await.unwrap();
+        assert_eq!(players.len(), 5);
+        assert_eq!(
+            players.iter().map(|p| p.games as i64).sum::<i64>(),
+            2 * matches
+        );
+        snapshot.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_backup_while_judging() {
+        let dir = std::env::temp_dir().join(format!("regan_backup_judging_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // the writer is the only connection
+        let ic = ImageCollection::new_pre_configured(5).await.unwrap();
+        backup_while_judging(&ic, &dir.join("exclusive")).await;
+        ic.close().await;
+
+        // the backup reads through the read pool
+        let image_dir = dir.join("images");
+        std::fs::create_dir_all(&image_dir).unwrap();
+        for i in 0..5 {
+            std::fs::write(image_dir.join(format!("{i}.png")), []).unwrap();
+        }
+        let options = ImageCollectionOptions {
+            db_path: format!("sqlite://{}", dir.join("out.db").display()),
+            read_connections: Some(1),
+            ..ImageCollectionOptions::default()
+        };
+        let ic = ImageCollection::new(&options, &image_dir.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        backup_while_judging(&ic, &dir.join("shared")).await;
+        ic.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backup_is_consistent_and_rotated() {
+        let ic = ImageCollection::new_pre_configured(5).await.unwrap();
+        let m = Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        };
+        ic.db
+            .update_rating(&m, &RatingModel::default())
+            .await
+            .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("regan_backup_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let options = BackupOptions {
+            dir: dir.clone(),
+            keep: 2,
+        };
+        let mut written = Vec::new();
+        for _ in 0..3 {
+            written.push(backup(&ic.db, &options).await.unwrap());
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert!(written[0].removed.is_empty());
+        assert_eq!(written[2].removed, vec![written[0].path.clone()]);
+        let mut files: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![written[1].path.clone(), written[2].path.clone()]
+        );
+
+        let snapshot = sqlx::sqlite::SqlitePoolOptions::new()
+            .connect_with(
+                sqlx::sqlite::SqliteConnectOptions::new()
+                    .filename(&written[2].path)
+                    .read_only(true),
+            )
+            .await
+            .unwrap();
+        let players = Db::Sqlite(snapshot.clone()).players().await.unwrap();
+        let expected = ic.db.players().await.unwrap();
+        assert_eq!(players.len(), 5);
+        assert_eq!(players[0].rating, expected[0].rating);
+        assert_eq!(players[0].games, 1);
+        snapshot.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Side;
+
+/// Elo scale, a rating difference of 400 points means 10:1 odds
+const Q_COEFF: f64 = std::f64::consts::LN_10 / 400_f64;
+
+/// Standard deviation of the gaussian prior of both effects in rating points.
+/// Keeps the estimate close to zero as long as there are only a few matches.
+const PRIOR_SIGMA: f64 = 100_f64;
+
+const NEWTON_ITERATIONS: usize = 20;
+
+/// Position and order effects of the annotators, estimated like a home advantage in sports.
+/// Both effects are given in rating points.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PositionBias {
+    /// advantage of the image displayed on the left
+    pub left: f64,
+    /// advantage of the image on the side which won the previous duel
+    pub repeat: f64,
+    /// number of matches with a known side the estimate is based on
+    pub matches: u64,
+}
+
+/// A decided or drawn match with a known displayed side
+pub struct Observation {
+    /// rating of the left image minus the rating of the right image
+    pub rating_diff: f64,
+    /// side which won the previous duel, None on a draw or if unknown
+    pub previous_winner: Option<Side>,
+    /// 1 if the left image won, 0.5 on draw, 0 if the right image won
+    pub left_score: f64,
+}
+
+fn side_sign(side: Option<Side>) -> f64 {
+    match side {
+        Some(Side::Left) => 1_f64,
+        Some(Side::Right) => -1_f64,
+        None => 0_f64,
+    }
+}
+
+impl PositionBias {
+    /// rating advantage of the image displayed on the left
+    pub fn left_advantage(&self, previous_winner: Option<Side>) -> f64 {
+        self.left + self.repeat * side_sign(previous_winner)
+    }
+
+    /// rating advantage of the home image. It is 0 if the displayed side is unknown.
+    pub fn home_advantage(&self, home_side: Option<Side>, previous_winner: Option<Side>) -> f64 {
+        match home_side {
+            Some(Side::Left) => self.left_advantage(previous_winner),
+            Some(Side::Right) => -self.left_advantage(previous_winner),
+            None => 0_f64,
+        }
+    }
+}
+
+/// Maximum a posteriori estimate of the position and order effect.
+///
+/// The probability that the left image wins is modelled as
+/// `1 / (1 + 10^(-(rating_diff + left + repeat * c) / 400))`,
+/// where `c` is 1 if the left side won the previous duel, -1 if the right side won it and 0 otherwise.
+pub fn estimate(observations: &[Observation]) -> PositionBias {
+    let prior = 1_f64 / (PRIOR_SIGMA * PRIOR_SIGMA);
+    let (mut left, mut repeat) = (0_f64, 0_f64);
+    for _ in 0..NEWTON_ITERATIONS {
+        // gradient and negated hessian of the log posterior
+        let (mut g_l, mut g_r) = (-left * prior, -repeat * prior);
+        let (mut h_ll, mut h_lr, mut h_rr) = (prior, 0_f64, prior);
+        for o in observations {
+            let c = side_sign(o.previous_winner);
+            let p = 1_f64 / (1_f64 + (-Q_COEFF * (o.rating_diff + left + repeat * c)).exp());
+            let residual = Q_COEFF * (o.left_score - p);
+            g_l += residual;
+            g_r += residual * c;
+            let w = Q_COEFF * Q_COEFF * p * (1_f64 - p);
+            h_ll += w;
+            h_lr += w * c;
+            h_rr += w * c * c;
+        }
+        let det = h_ll * h_rr - h_lr * h_lr;
+        let step_l = (h_rr * g_l - h_lr * g_r) / det;
+        let step_r = (h_ll * g_r - h_lr * g_l) / det;
+        left += step_l;
+        repeat += step_r;
+        if step_l.abs() < 1e-6 && step_r.abs() < 1e-6 {
+            break;
+        }
+    }
+    PositionBias {
+        left,
+        repeat,
+        matches: observations.len() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_no_observations() {
+        assert_eq!(estimate(&[]), PositionBias::default());
+    }
+
+    #[test]
+    fn test_home_advantage() {
+        let bias = PositionBias {
+            left: 20.0,
+            repeat: 5.0,
+            matches: 0,
+        };
+        assert_eq!(bias.home_advantage(None, Some(Side::Left)), 0.0);
+        assert_eq!(
+            bias.home_advantage(Some(Side::Left), Some(Side::Left)),
+            25.0
+        );
+        assert_eq!(
+            bias.home_advantage(Some(Side::Left), Some(Side::Right)),
+            15.0
+        );
+        assert_eq!(bias.home_advantage(Some(Side::Right), None), -20.0);
+    }
+
+    #[test]
+    fn test_estimate_recovers_simulated_bias() {
+        let mut rng = rand::rng();
+        let (true_left, true_repeat) = (60_f64, -30_f64);
+        let mut previous_winner = None;
+        let observations: Vec<Observation> = (0..20000)
+            .map(|_| {
+                let rating_diff = rng.random_range(-300_f64..300_f64);
+                let c = side_sign(previous_winner);
+                let p = 1_f64
+                    / (1_f64 + (-Q_COEFF * (rating_diff + true_left + true_repeat * c)).exp());
+                let left_won = rng.random_bool(p);
+                let o = Observation {
+                    rating_diff,
+                    previous_winner,
+                    left_score: if left_won { 1_f64 } else { 0_f64 },
+                };
+                previous_winner = Some(if left_won { Side::Left } else { Side::Right });
+                o
+            })
+            .collect();
+        let bias = estimate(&observations);
+        assert_eq!(bias.matches, 20000);
+        assert!((bias.left - true_left).abs() < 10.0, "{bias:?}");
+        assert!((bias.repeat - true_repeat).abs() < 10.0, "{bias:?}");
+    }
+}
@@ -0,0 +1,120 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// A rated match of one annotator
+pub struct Judgement {
+    pub home: i64,
+    pub guest: i64,
+    /// score of the home player
+    pub score: f64,
+}
+
+/// Consistency of the judgements of one annotator
+#[derive(Debug, Default, PartialEq)]
+pub struct Consistency {
+    /// judgements of pairs, which the annotator judged before
+    pub repeated: u64,
+    /// repeated judgements which prefer the same image as the previous judgement of the pair, or both are draws
+    pub agreeing: u64,
+    /// triads of images, whose preferences form a cycle like a > b > c > a
+    pub intransitive_cycles: u64,
+}
+
+/// compares the judgements in the given order.
+/// For the cycles, every pair counts with its net preference over all judgements.
+pub fn check(judgements: &[Judgement]) -> Consistency {
+    let mut consistency = Consistency::default();
+    // preference of the lower id for every pair, its last judgement and the sum over all judgements
+    let mut pairs = HashMap::<(i64, i64), (Ordering, f64)>::new();
+    for j in judgements {
+        let (key, score) = if j.home < j.guest {
+            ((j.home, j.guest), j.score)
+        } else {
+            ((j.guest, j.home), 1_f64 - j.score)
+        };
+        let preference = score.partial_cmp(&0.5).unwrap_or(Ordering::Equal);
+        match pairs.get_mut(&key) {
+            Some((last, net)) => {
+                consistency.repeated += 1;
+                if *last == preference {
+                    consistency.agreeing += 1;
+                }
+                *last = preference;
+                *net += score - 0.5_f64;
+            }
+            None => {
+                pairs.insert(key, (preference, score - 0.5_f64));
+            }
+        }
+    }
+
+    // directed graph from the better to the worse image
+    let mut better = HashMap::<i64, HashSet<i64>>::new();
+    for (&(a, b), &(_, net)) in &pairs {
+        if net > 0_f64 {
+            better.entry(a).or_default().insert(b);
+        } else if net < 0_f64 {
+            better.entry(b).or_default().insert(a);
+        }
+    }
+    let mut cycles = 0_u64;
+    for (&a, worse) in &better {
+        for &b in worse {
+            for &c in better.get(&b).into_iter().flatten() {
+                if better.get(&c).is_some_and(|w| w.contains(&a)) {
+                    cycles += 1;
+                }
+            }
+        }
+    }
+    // every cycle is found from each of its three images
+    consistency.intransitive_cycles = cycles / 3;
+    consistency
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn judgement(home: i64, guest: i64, score: f64) -> Judgement {
+        Judgement { home, guest, score }
+    }
+
+    #[test]
+    fn test_repeated_judgements() {
+        let consistency = check(&[
+            judgement(1, 2, 1.0),
+            // swapped sides, same preference
+            judgement(2, 1, 0.25),
+            // changed its mind
+            judgement(1, 2, 0.0),
+            judgement(3, 4, 0.5),
+            judgement(4, 3, 0.5),
+        ]);
+        assert_eq!(consistency.repeated, 3);
+        assert_eq!(consistency.agreeing, 2);
+        assert_eq!(consistency.intransitive_cycles, 0);
+    }
+
+    #[test]
+    fn test_intransitive_cycles() {
+        let transitive = check(&[
+            judgement(1, 2, 1.0),
+            judgement(2, 3, 1.0),
+            judgement(1, 3, 1.0),
+        ]);
+        assert_eq!(transitive.intransitive_cycles, 0);
+
+        let cyclic = check(&[
+            judgement(1, 2, 1.0),
+            judgement(2, 3, 0.75),
+            judgement(1, 3, 0.0),
+            // a second cycle sharing the pair 1-2
+            judgement(2, 4, 1.0),
+            judgement(4, 1, 1.0),
+            // draws are no preference
+            judgement(3, 4, 0.5),
+        ]);
+        assert_eq!(cyclic.intransitive_cycles, 2);
+    }
+}
@@ -0,0 +1,95 @@
+/// Logistic approximation of the standard normal cdf.
+fn normal_cdf(x: f64) -> f64 {
+    1_f64 / (1_f64 + (-1.702_f64 * x).exp())
+}
+
+/// Pairs further apart than this many standard deviations are treated as certainly ordered.
+const CUTOFF_SIGMA: f64 = 8_f64;
+
+/// Estimates the root mean squared rank error from the ratings and their deviations.
+///
+/// For each pair, the probability that their rating order is flipped is derived from the deviations.
+/// The rank displacement of a player is the number of lower rated players which truly are better
+/// minus the number of higher rated players which truly are worse,
+/// its expected square is summed up from these probabilities.
+/// The result is comparable to the MSRE of the simulation, where the true order is known.
+pub fn rank_error(ratings: &[(f64, f64)]) -> f64 {
+    if ratings.is_empty() {
+        return 0_f64;
+    }
+    let mut sorted = ratings.to_vec();
+    sorted.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let max_rd = sorted.iter().map(|r| r.1).fold(0_f64, f64::max);
+
+    // expected displacement and its variance per player
+    let mut mean = vec![0_f64; sorted.len()];
+    let mut variance = vec![0_f64; sorted.len()];
+    for i in 0..sorted.len() {
+        let (r_i, rd_i) = sorted[i];
+        let reach = CUTOFF_SIGMA * (rd_i * rd_i + max_rd * max_rd).sqrt();
+        for j in i + 1..sorted.len() {
+            let (r_j, rd_j) = sorted[j];
+            let diff = r_j - r_i;
+            if diff > reach {
+                break;
+            }
+            let sigma = (rd_i * rd_i + rd_j * rd_j).sqrt();
+            let p = if sigma > 0_f64 {
+                normal_cdf(-diff / sigma)
+            } else {
+                0_f64
+            };
+            mean[i] += p;
+            mean[j] -= p;
+            variance[i] += p * (1_f64 - p);
+            variance[j] += p * (1_f64 - p);
+        }
+    }
+    let sqr: f64 = mean
+        .iter()
+        .zip(variance.iter())
+        .map(|(m, v)| m * m + v)
+        .sum();
+    (sqr / sorted.len() as f64).sqrt()
+}
+
+/// [`rank_error`] of `n` unrated players, which all share the same rating and deviation.
+pub fn initial_rank_error(n: usize) -> f64 {
+    if n == 0 {
+        return 0_f64;
+    }
+    let n = n as f64;
+    ((n * n - 1_f64) / 12_f64 + (n - 1_f64) / 4_f64).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_error_empty() {
+        assert_eq!(rank_error(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_rank_error_separated_players() {
+        let ratings = [(1000.0, 10.0), (2000.0, 10.0), (3000.0, 10.0)];
+        assert!(rank_error(&ratings) < 1e-6);
+    }
+
+    #[test]
+    fn test_rank_error_unrated_players() {
+        // all players are equal, so every pair is a coin flip
+        let ratings = vec![(2200.0, 350.0); 11];
+        let e = rank_error(&ratings);
+        assert!((e - 12.5_f64.sqrt()).abs() < 1e-9, "{e}");
+        assert!((e - initial_rank_error(11)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rank_error_decreases_with_deviation() {
+        let wide: Vec<(f64, f64)> = (0..50).map(|i| (i as f64 * 20.0, 200.0)).collect();
+        let narrow: Vec<(f64, f64)> = (0..50).map(|i| (i as f64 * 20.0, 50.0)).collect();
+        assert!(rank_error(&narrow) < rank_error(&wide));
+    }
+}
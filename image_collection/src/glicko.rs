@@ -29,10 +29,13 @@ fn e_f(rating_this: f64, rating_other: f64, rd_other: f64) -> f64 {
 
 /// Glicko-1 rating algorithm (Glickman, 1995).
 /// Operates on raw rating/scale directly (not the reduced Glicko-2 scale).
+/// The game counts `weight` times: a weight of 2 changes the rating like two games
+/// with the same result, 0 like no game.
 pub fn new_rating(
     r_home: &Rating,
     r_other: &Rating,
     home_won: f64,
+    weight: f64,
     time: usize,
     decay_factor: f64,
 ) -> Rating {
@@ -40,12 +43,13 @@ pub fn new_rating(
     let rd = time_decay(r_home.deviation, time_delta, decay_factor);
     let g_rdi = g_rd(r_other.deviation);
     let e = e_f(r_home.rating, r_other.rating, r_other.deviation);
-    let d_sqr_inv = Q_COEFF_SQR * g_rdi * g_rdi * e * (1_f64 - e);
+    let weight = weight.max(0_f64);
+    let d_sqr_inv = weight * Q_COEFF_SQR * g_rdi * g_rdi * e * (1_f64 - e);
     let dif = 1_f64 / (rd * rd) + d_sqr_inv;
     let dif_inv = 1_f64 / dif;
     let coeff = Q_COEFF * dif_inv;
     let home_won = home_won.clamp(0.0, 1.0);
-    let new_rating = r_home.rating + coeff * g_rdi * (home_won - e);
+    let new_rating = r_home.rating + coeff * weight * g_rdi * (home_won - e);
     let new_rd = dif_inv.sqrt().min(350_f64);
     Rating {
         deviation: new_rd,
@@ -54,6 +58,19 @@ pub fn new_rating(
     }
 }
 
+/// Estimates the number of games a player with deviation `rd` needs to reach the `target` deviation.
+/// Assumes opponents with a similar rating and the deviation `rd_opponent`,
+/// which is the most informative game possible.
+pub fn games_to_deviation(rd: f64, target: f64, rd_opponent: f64) -> f64 {
+    if rd <= target {
+        return 0_f64;
+    }
+    let g = g_rd(rd_opponent);
+    // e = 0.5 for equally rated opponents
+    let d_sqr_inv = Q_COEFF_SQR * g * g * 0.25_f64;
+    (1_f64 / (target * target) - 1_f64 / (rd * rd)) / d_sqr_inv
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,12 +105,41 @@ mod tests {
             deviation: 30.0,
             time: 0,
         };
-        let nr = new_rating(&h, &g, 1.0, 0, 0_f64);
+        let nr = new_rating(&h, &g, 1.0, 1.0, 0, 0_f64);
         assert_delta!(nr.rating, 1563.43, 0.1);
         assert_delta!(nr.deviation, 175.22, 0.1);
         assert_eq!(nr.time, 0);
     }
 
+    #[test]
+    fn test_weighted_rating() {
+        let h = Rating {
+            rating: 1500.0,
+            deviation: 200.0,
+            time: 0,
+        };
+        let g = Rating {
+            rating: 1400.0,
+            deviation: 30.0,
+            time: 0,
+        };
+        let single = new_rating(&h, &g, 1.0, 1.0, 0, 0_f64);
+        let none = new_rating(&h, &g, 1.0, 0.0, 0, 0_f64);
+        assert_delta!(none.rating, h.rating, 1e-9);
+        assert_delta!(none.deviation, h.deviation, 1e-9);
+
+        // a double weight shrinks the deviation like two games against the opponent
+        let double = new_rating(&h, &g, 1.0, 2.0, 0, 0_f64);
+        assert!(double.rating > single.rating);
+        assert!(double.deviation < single.deviation);
+        let d_sqr_inv = 1.0 / (single.deviation * single.deviation) - 1.0 / (200.0 * 200.0);
+        let expected = (1.0 / (1.0 / (200.0 * 200.0) + 2.0 * d_sqr_inv)).sqrt();
+        assert_delta!(double.deviation, expected, 1e-9);
+
+        let half = new_rating(&h, &g, 1.0, 0.5, 0, 0_f64);
+        assert!(half.rating > h.rating && half.rating < single.rating);
+    }
+
     #[test]
     fn test_time_decay_no_decay() {
         let rd = time_decay(200.0, 0.0, 0.0);
@@ -143,8 +189,8 @@ mod tests {
             deviation: 200.0,
             time: 0,
         };
-        let win = new_rating(&a, &b, 1.0, 0, 0_f64);
-        let loss = new_rating(&a, &b, 0.0, 0, 0_f64);
+        let win = new_rating(&a, &b, 1.0, 1.0, 0, 0_f64);
+        let loss = new_rating(&a, &b, 0.0, 1.0, 0, 0_f64);
         assert_delta!(win.rating - a.rating, -(loss.rating - a.rating), 0.001);
         assert_delta!(win.deviation, loss.deviation, 1e-12);
     }
@@ -161,12 +207,12 @@ mod tests {
             deviation: 30.0,
             time: 0,
         };
-        let win = new_rating(&h, &g, 1.0, 0, 0_f64);
-        let overshoot = new_rating(&h, &g, 2.0, 0, 0_f64);
+        let win = new_rating(&h, &g, 1.0, 1.0, 0, 0_f64);
+        let overshoot = new_rating(&h, &g, 2.0, 1.0, 0, 0_f64);
         assert_eq!(win.rating, overshoot.rating);
         assert_eq!(win.deviation, overshoot.deviation);
-        let loss = new_rating(&h, &g, 0.0, 0, 0_f64);
-        let undershoot = new_rating(&h, &g, -1.0, 0, 0_f64);
+        let loss = new_rating(&h, &g, 0.0, 1.0, 0, 0_f64);
+        let undershoot = new_rating(&h, &g, -1.0, 1.0, 0, 0_f64);
         assert_eq!(loss.rating, undershoot.rating);
         assert_eq!(loss.deviation, undershoot.deviation);
     }
@@ -192,7 +238,7 @@ mod tests {
                 deviation: rd_g,
                 time: 0,
             };
-            let nr = new_rating(&h, &g, s, 0, 0_f64);
+            let nr = new_rating(&h, &g, s, 1.0, 0, 0_f64);
             assert!(
                 nr.deviation <= 350.0,
                 "deviation {0} exceeds 350",
@@ -201,4 +247,24 @@ mod tests {
             assert!(nr.deviation >= 0.0);
         }
     }
+
+    #[test]
+    fn test_games_to_deviation_matches_new_rating() {
+        let opponent = Rating {
+            rating: 1500.0,
+            deviation: 50.0,
+            time: 0,
+        };
+        let mut r = Rating {
+            rating: 1500.0,
+            deviation: 350.0,
+            time: 0,
+        };
+        for _ in 0..10 {
+            r = new_rating(&r, &opponent, 0.5, 1.0, 0, 0_f64);
+        }
+        let games = games_to_deviation(350.0, r.deviation, 50.0);
+        assert_delta!(games, 10.0, 1e-6);
+        assert_eq!(games_to_deviation(100.0, 150.0, 50.0), 0.0);
+    }
 }
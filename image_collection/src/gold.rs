@@ -0,0 +1,138 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::MatchOutcome;
+use crate::pair_csv::{self, PairRow};
+
+/// A pair of images with a known result, used to check the annotators
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct GoldPair {
+    pub home: String,
+    pub guest: String,
+    /// expected result in terms of the home image
+    pub result: MatchOutcome,
+}
+
+impl PairRow for GoldPair {
+    fn result(&self) -> MatchOutcome {
+        self.result
+    }
+}
+
+/// reads gold pairs from a csv file with the header "home,guest,result"
+pub fn read_csv(path: &Path) -> Result<Vec<GoldPair>> {
+    pair_csv::read(path, "gold pair")
+}
+
+/// true, if the answer prefers the same image as the expected result, or both are draws
+pub fn is_correct(expected: f64, answer: f64) -> bool {
+    expected.partial_cmp(&0.5) == answer.partial_cmp(&0.5)
+}
+
+/// Handling of annotators whose accuracy on the gold pairs is too low
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LowAccuracy {
+    /// their results count as a fraction of a game, down to no game at chance level
+    DownWeight,
+    /// their results are recorded, but do not change the ratings
+    Exclude,
+}
+
+impl FromStr for LowAccuracy {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "down-weight" => Ok(LowAccuracy::DownWeight),
+            "exclude" => Ok(LowAccuracy::Exclude),
+            _ => Err("must be \"down-weight\" or \"exclude\""),
+        }
+    }
+}
+
+impl std::fmt::Display for LowAccuracy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LowAccuracy::DownWeight => write!(f, "down-weight"),
+            LowAccuracy::Exclude => write!(f, "exclude"),
+        }
+    }
+}
+
+/// Minimal accuracy of the annotators on the gold pairs
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QualityControl {
+    /// share of correct answers, must be above chance level (0.5)
+    pub min_accuracy: f64,
+    /// annotators with less gold answers are trusted
+    pub min_answers: u64,
+    pub low_accuracy: LowAccuracy,
+}
+
+impl QualityControl {
+    /// weight of the results of an annotator between 0 and 1
+    pub fn weight(&self, answers: u64, correct: u64) -> f64 {
+        if answers == 0 || answers < self.min_answers {
+            return 1_f64;
+        }
+        let accuracy = correct as f64 / answers as f64;
+        if accuracy >= self.min_accuracy {
+            return 1_f64;
+        }
+        match self.low_accuracy {
+            LowAccuracy::Exclude => 0_f64,
+            LowAccuracy::DownWeight => {
+                ((accuracy - 0.5_f64) / (self.min_accuracy - 0.5_f64)).clamp(0_f64, 1_f64)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pair_csv::TempCsv;
+
+    #[test]
+    fn test_read_csv() {
+        let csv = TempCsv::new("gold");
+        let pairs =
+            read_csv(csv.write("home,guest,result\na.png, b.png ,1\nc.png,d.png,0.25\n")).unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].guest, "b.png");
+        assert_eq!(pairs[0].result, MatchOutcome::HomeWin);
+        assert_eq!(pairs[1].result, MatchOutcome::GuestSlightlyBetter);
+
+        assert!(read_csv(csv.write("home,guest,result\na.png,b.png,skip\n")).is_err());
+        assert!(read_csv(csv.write("home,guest,result\na.png,b.png,0.3\n")).is_err());
+    }
+
+    #[test]
+    fn test_is_correct() {
+        assert!(is_correct(1.0, 0.75));
+        assert!(is_correct(0.5, 0.5));
+        assert!(is_correct(0.0, 0.25));
+        assert!(!is_correct(1.0, 0.5));
+        assert!(!is_correct(0.0, 1.0));
+    }
+
+    #[test]
+    fn test_weight() {
+        let mut qc = QualityControl {
+            min_accuracy: 0.8,
+            min_answers: 10,
+            low_accuracy: LowAccuracy::DownWeight,
+        };
+        // not enough answers to judge
+        assert_eq!(qc.weight(5, 0), 1.0);
+        assert_eq!(qc.weight(10, 9), 1.0);
+        assert!((qc.weight(10, 7) - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(qc.weight(10, 4), 0.0);
+        qc.low_accuracy = LowAccuracy::Exclude;
+        assert_eq!(qc.weight(10, 7), 0.0);
+        assert_eq!(qc.weight(10, 8), 1.0);
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow, bail};
+use serde::Deserialize;
+
+use crate::MatchOutcome;
+use crate::pair_csv::{self, PairRow};
+
+/// A judgement made outside of the server, e.g. in a spreadsheet
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportedMatch {
+    pub home: String,
+    pub guest: String,
+    /// result in terms of the home image
+    pub result: MatchOutcome,
+    /// time of the judgement as "YYYY-MM-DD HH:MM", the time of the import if not given
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub annotator: Option<String>,
+}
+
+impl PairRow for ImportedMatch {
+    fn result(&self) -> MatchOutcome {
+        self.result
+    }
+
+    fn validate(&mut self) -> Result<()> {
+        if self.home == self.guest {
+            bail!("the match compares an image with itself");
+        }
+        self.timestamp = match self.timestamp.as_deref().filter(|t| !t.is_empty()) {
+            Some(timestamp) => Some(
+                normalize_timestamp(timestamp)
+                    .ok_or_else(|| anyhow!("invalid timestamp \"{}\"", timestamp))?,
+            ),
+            None => None,
+        };
+        self.annotator = self.annotator.take().filter(|a| !a.is_empty());
+        Ok(())
+    }
+}
+
+/// reads judgements from a csv file with the header "home,guest,result[,timestamp,annotator]".
+/// Timestamps are accepted as "YYYY-MM-DD", "YYYY-MM-DD HH:MM[:SS]" or with a "T" as separator.
+pub fn read_csv(path: &Path) -> Result<Vec<ImportedMatch>> {
+    pair_csv::read(path, "match")
+}
+
+/// brings a date with an optional time into the format of the database, "YYYY-MM-DD HH:MM"
+fn normalize_timestamp(timestamp: &str) -> Option<String> {
+    let is_number = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_digit());
+    let (date, time) = match timestamp.split_once([' ', 'T']) {
+        Some((date, time)) => (date, time.trim()),
+        None => (timestamp, "00:00"),
+    };
+    let mut date_parts = date.split('-');
+    let (year, month, day) = (date_parts.next()?, date_parts.next()?, date_parts.next()?);
+    let mut time_parts = time.split(':');
+    let (hour, minute) = (time_parts.next()?, time_parts.next()?);
+    let seconds = time_parts.next();
+    let valid = date_parts.next().is_none()
+        && time_parts.next().is_none()
+        && is_number(year, 4)
+        && is_number(month, 2)
+        && is_number(day, 2)
+        && is_number(hour, 2)
+        && is_number(minute, 2)
+        && seconds.is_none_or(|s| is_number(s, 2))
+        && (1..=12).contains(&month.parse::<u32>().ok()?)
+        && (1..=31).contains(&day.parse::<u32>().ok()?)
+        && hour.parse::<u32>().ok()? < 24
+        && minute.parse::<u32>().ok()? < 60;
+    valid.then(|| format!("{year}-{month}-{day} {hour}:{minute}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pair_csv::TempCsv;
+
+    #[test]
+    fn test_read_csv() {
+        let csv = TempCsv::new("import");
+        let matches = read_csv(csv.write(
+            "home,guest,result,timestamp,annotator\n\
+            a.png, b.png ,1,2024-03-01 14:05:59,alice\n\
+            c.png,d.png,0.25,,\n\
+            b.png,c.png,0.5,2024-03-02,bob\n",
+        ))
+        .unwrap();
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[0].guest, "b.png");
+        assert_eq!(matches[0].result, MatchOutcome::HomeWin);
+        assert_eq!(matches[0].timestamp.as_deref(), Some("2024-03-01 14:05"));
+        assert_eq!(matches[0].annotator.as_deref(), Some("alice"));
+        assert_eq!(matches[1].timestamp, None);
+        assert_eq!(matches[1].annotator, None);
+        assert_eq!(matches[2].timestamp.as_deref(), Some("2024-03-02 00:00"));
+
+        let matches = read_csv(csv.write("home,guest,result\na.png,b.png,0\n")).unwrap();
+        assert_eq!(matches[0].result, MatchOutcome::GuestWin);
+        assert_eq!(matches[0].timestamp, None);
+
+        for invalid in [
+            "home,guest,result\na.png,b.png,skip\n",
+            "home,guest,result\na.png,a.png,1\n",
+            "home,guest,result,timestamp\na.png,b.png,1,yesterday\n",
+        ] {
+            assert!(read_csv(csv.write(invalid)).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_normalize_timestamp() {
+        assert_eq!(
+            normalize_timestamp("2024-03-01T09:30").as_deref(),
+            Some("2024-03-01 09:30")
+        );
+        assert_eq!(normalize_timestamp("2024-13-01"), None);
+        assert_eq!(normalize_timestamp("2024-03-01 25:00"), None);
+        assert_eq!(normalize_timestamp("01.03.2024"), None);
+    }
+}
@@ -1,12 +1,31 @@
+mod backup;
+mod bias;
+mod consistency;
+mod convergence;
 #[macro_use]
 mod glicko;
-
-use anyhow::Result;
+mod gold;
+mod import;
+mod memory;
+mod migrations;
+mod pair_csv;
+mod session;
+mod storage;
+mod swiss;
+
+pub use backup::{Backup, BackupOptions};
+pub use bias::PositionBias;
+pub use gold::{GoldPair, LowAccuracy, QualityControl, read_csv as read_gold_pairs};
+pub use import::{ImportedMatch, read_csv as read_matches};
+pub use memory::MemoryStorage;
+pub use session::{SessionLimits, SessionStatus};
+pub use storage::{Storage, is_postgres_url};
+
+use anyhow::{Result, bail};
 use crossbeam_queue::ArrayQueue;
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
 use std::str::FromStr;
-use tokio_stream::StreamExt;
+use storage::{Db, Tx, judged_pairs, update_rating_at};
 
 use rand::prelude::*;
 use tracing::*;
@@ -18,12 +37,87 @@ pub struct ImageCollection {
     /// is true, when a thread is already in process in filling the queue up
     db_update_in_progress: std::sync::Arc<std::sync::atomic::AtomicBool>,
 
-    db: SqlitePool,
+    db: Db,
+    /// read-only connections for exports and statistics, or the writer without a read pool
+    reader: Db,
+    matchmaking: Matchmaking,
+    stopping: StoppingCriterion,
+    /// is true, when the stopping criterion was reached
+    complete: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// position and order bias, which gets removed from the rating updates
+    bias: std::sync::Arc<std::sync::RwLock<PositionBias>>,
+    correct_position_bias: bool,
+    response_time_weighting: Option<ResponseTimeWeighting>,
+    /// matches inserted since the last estimate of the position bias
+    matches_since_bias_estimate: std::sync::Arc<std::sync::atomic::AtomicU64>,
+    /// matches inserted since the start, the stopping criterion is evaluated every few matches
+    matches_since_start: std::sync::Arc<std::sync::atomic::AtomicU64>,
+    /// pairs with a known result, which are interleaved to check the annotators
+    gold: std::sync::Arc<Vec<GoldDuel>>,
+    gold_rate: f64,
+    quality_control: Option<QualityControl>,
+    /// share of the duels which repeat a judged pair or close a triad of judged pairs
+    consistency_rate: f64,
+    sessions: SessionLimits,
+    backup: Option<BackupOptions>,
+    /// held while a backup runs, the pools may only close when no backup uses them
+    backup_running: std::sync::Arc<tokio::sync::Mutex<()>>,
+}
+
+/// A gold pair with the ids of its players
+#[derive(Clone, Debug)]
+struct GoldDuel {
+    home: String,
+    home_id: u32,
+    guest: String,
+    guest_id: u32,
+    /// expected score of the home player
+    result: f64,
+}
+
+/// the position bias gets re-estimated after this number of matches
+const BIAS_ESTIMATE_INTERVAL: u64 = 25;
+
+/// the stopping criterion gets evaluated on the first match and after this number of matches.
+/// The game budget is checked after every match.
+const STATUS_CHECK_INTERVAL: u64 = 10;
+
+/// Strategy used to select the next duels
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Matchmaking {
+    /// pairs the players with the highest deviation with a random opponent
+    /// inside of their confidence interval
+    #[default]
+    Random,
+    /// deterministic Swiss-system rounds.
+    /// Every player plays once per round against the closest rated player it has not met yet.
+    /// Useful for small sets, where every image should get the same number of comparisons.
+    Swiss,
+}
+
+impl FromStr for Matchmaking {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Matchmaking::Random),
+            "swiss" => Ok(Matchmaking::Swiss),
+            _ => Err("matchmaking must be \"random\" or \"swiss\""),
+        }
+    }
+}
+
+impl std::fmt::Display for Matchmaking {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Matchmaking::Random => write!(f, "random"),
+            Matchmaking::Swiss => write!(f, "swiss"),
+        }
+    }
 }
 
 /// Options to create an ImageCollection Type
 pub struct ImageCollectionOptions {
-    /// path to the sqlite db
+    /// url of the database, a sqlite path or a postgres:// url
     pub db_path: String,
     /// size of the pre-computation buffer
     /// the buffer holds possible candidates in a prio queue.
@@ -32,8 +126,359 @@ pub struct ImageCollectionOptions {
     /// leading to a possible worse machtmaking with a too huge caching.
     /// It is used to reduce the queries to the db.
     pub candidate_buffer: usize,
+    /// strategy used to select the next duels
+    pub matchmaking: Matchmaking,
+    /// when the campaign is considered as done
+    pub stopping: StoppingCriterion,
+    /// estimates the left/right and order bias of the annotators
+    /// and removes it from the rating updates
+    pub correct_position_bias: bool,
+    /// uses the response times as confidence of the results
+    pub response_time_weighting: Option<ResponseTimeWeighting>,
+    /// pairs with a known result to check the annotators
+    pub gold_pairs: Vec<GoldPair>,
+    /// share of the served duels which are gold pairs
+    pub gold_rate: f64,
+    /// down-weights or excludes annotators with a low accuracy on the gold pairs
+    pub quality_control: Option<QualityControl>,
+    /// share of the served duels which check the consistency of the annotators,
+    /// either by repeating a judged pair or by closing a triad of judged pairs
+    pub consistency_rate: f64,
+    /// limits of the annotation sessions, which are tracked for every annotator
+    pub sessions: SessionLimits,
+    /// number of read-only sqlite connections besides the single writer,
+    /// so exports and statistics don't block the annotators.
+    /// None locks the database exclusively, no other process can read it while running.
+    pub read_connections: Option<u32>,
+    /// directory of the snapshots of the sqlite database, backups are disabled without
+    pub backup: Option<BackupOptions>,
+}
+
+impl Default for ImageCollectionOptions {
+    fn default() -> Self {
+        ImageCollectionOptions {
+            db_path: "sqlite://out.db".to_string(),
+            candidate_buffer: 20,
+            matchmaking: Matchmaking::default(),
+            stopping: StoppingCriterion::default(),
+            correct_position_bias: false,
+            response_time_weighting: None,
+            gold_pairs: Vec::new(),
+            gold_rate: 0_f64,
+            quality_control: None,
+            consistency_rate: 0_f64,
+            sessions: SessionLimits::default(),
+            read_connections: None,
+            backup: None,
+        }
+    }
+}
+
+/// Criterion to decide when a campaign is done.
+/// The campaign is complete as soon as one of the given limits is reached.
+/// Without any limit, the campaign never completes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StoppingCriterion {
+    /// complete when the highest deviation of all players is below this value
+    pub max_deviation: Option<f64>,
+    /// complete when the estimated root mean squared rank error is below this value
+    pub max_rank_error: Option<f64>,
+    /// complete after this number of played games
+    pub max_games: Option<u64>,
+}
+
+impl StoppingCriterion {
+    pub fn is_set(&self) -> bool {
+        self.max_deviation.is_some() || self.max_rank_error.is_some() || self.max_games.is_some()
+    }
+}
+
+/// Uses the response time of a match as confidence of its result.
+/// Fast decisions count fully, slow decisions are treated as partial draws.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResponseTimeWeighting {
+    /// decisions up to this response time count fully
+    pub fast_ms: u32,
+    /// decisions from this response time on count with `min_confidence`
+    pub slow_ms: u32,
+    /// share of the result which counts for slow decisions, the rest counts as draw
+    pub min_confidence: f64,
+}
+
+impl ResponseTimeWeighting {
+    /// between `min_confidence` and 1, linear between `fast_ms` and `slow_ms`
+    pub fn confidence(&self, response_ms: u32) -> f64 {
+        if response_ms <= self.fast_ms {
+            return 1_f64;
+        }
+        if response_ms >= self.slow_ms {
+            return self.min_confidence;
+        }
+        let t = (response_ms - self.fast_ms) as f64 / (self.slow_ms - self.fast_ms) as f64;
+        1_f64 - t * (1_f64 - self.min_confidence)
+    }
+
+    /// moves the score towards a draw by the missing confidence.
+    /// Scores without a response time are kept.
+    pub fn score(&self, score: f64, response_ms: Option<u32>) -> f64 {
+        match response_ms {
+            Some(ms) => 0.5_f64 + (score - 0.5_f64) * self.confidence(ms),
+            None => score,
+        }
+    }
+}
+
+impl FromStr for ResponseTimeWeighting {
+    type Err = String;
+    /// "<fast_ms>,<slow_ms>[,<min_confidence>]", the minimal confidence defaults to 0.5
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err =
+            || format!("invalid response time weighting \"{s}\", expected e.g. \"2000,15000,0.5\"");
+        let parts: Vec<&str> = s.split(',').map(|p| p.trim()).collect();
+        let (fast, slow, min) = match parts[..] {
+            [fast, slow] => (fast, slow, "0.5"),
+            [fast, slow, min] => (fast, slow, min),
+            _ => return Err(err()),
+        };
+        let weighting = ResponseTimeWeighting {
+            fast_ms: fast.parse().map_err(|_| err())?,
+            slow_ms: slow.parse().map_err(|_| err())?,
+            min_confidence: min.parse().map_err(|_| err())?,
+        };
+        if weighting.fast_ms >= weighting.slow_ms
+            || !(0_f64..=1_f64).contains(&weighting.min_confidence)
+        {
+            return Err(err());
+        }
+        Ok(weighting)
+    }
+}
+
+/// Adjustments of the rating update
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RatingModel {
+    pub bias: PositionBias,
+    pub response_time: Option<ResponseTimeWeighting>,
+    pub quality_control: Option<QualityControl>,
+}
+
+impl RatingModel {
+    /// new ratings of the home and guest player after the match.
+    /// The home player is expected to perform better or worse by the position and order bias.
+    /// `weight` is the weight of the annotator by its accuracy on the gold pairs,
+    /// `game_weight` the weight of its games. Both scale how much the game counts.
+    /// None, if the results of the annotator are excluded.
+    fn rate(
+        &self,
+        home: &glicko::Rating,
+        guest: &glicko::Rating,
+        m: &Match,
+        previous_winner: Option<Side>,
+        weight: f64,
+        game_weight: f64,
+    ) -> Result<Option<(glicko::Rating, glicko::Rating)>> {
+        let advantage = self.bias.home_advantage(m.home_side, previous_winner);
+        // each player faces the opponent as it appeared with the bias
+        let home_biased = glicko::Rating {
+            rating: home.rating + advantage,
+            ..*home
+        };
+        let guest_biased = glicko::Rating {
+            rating: guest.rating - advantage,
+            ..*guest
+        };
+        let Some(score) = m.won.score() else {
+            bail!("match outcome {} does not change the ratings", m.won);
+        };
+        let won_home = match self.response_time {
+            Some(weighting) => weighting.score(score, m.response_ms),
+            None => score,
+        };
+        // results of unreliable annotators count as a fraction of a game
+        let game_weight = weight * game_weight;
+        if game_weight == 0_f64 {
+            return Ok(None);
+        }
+        Ok(Some((
+            glicko::new_rating(home, &guest_biased, won_home, game_weight, 0, 0_f64),
+            glicko::new_rating(guest, &home_biased, 1.0 - won_home, game_weight, 0, 0_f64),
+        )))
+    }
+}
+
+/// Progress of the campaign towards its stopping criterion
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CampaignStatus {
+    /// true, if no more duels are served
+    pub complete: bool,
+    /// progress towards the closest limit of the stopping criterion, between 0 and 1.
+    /// None, if no stopping criterion is set.
+    pub progress: Option<f64>,
+    pub players: u64,
+    pub games: u64,
+    /// highest deviation of all players
+    pub max_deviation: f64,
+    /// estimated root mean squared rank error
+    pub rank_error: f64,
+    pub criterion: StoppingCriterion,
+}
+
+/// A player with its current rating
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerInfo {
+    pub id: u32,
+    pub name: String,
+    pub rating: f64,
+    pub deviation: f64,
+    pub games: u64,
+    /// flagged for review by an annotator, not part of the matchmaking
+    pub flagged: bool,
+}
+
+/// Sort key of the player list
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerOrder {
+    #[default]
+    Rating,
+    Deviation,
+    Games,
+}
+
+impl std::fmt::Display for PlayerOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayerOrder::Rating => write!(f, "rating"),
+            PlayerOrder::Deviation => write!(f, "deviation"),
+            PlayerOrder::Games => write!(f, "games"),
+        }
+    }
+}
+
+/// Selects a page of the player list
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerQuery {
+    pub order_by: PlayerOrder,
+    pub descending: bool,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+impl Default for PlayerQuery {
+    fn default() -> Self {
+        PlayerQuery {
+            order_by: PlayerOrder::Rating,
+            descending: true,
+            offset: 0,
+            limit: 50,
+        }
+    }
+}
+
+/// A page of the player list
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerPage {
+    /// number of all players
+    pub total: u64,
+    pub players: Vec<PlayerInfo>,
+}
+
+/// A played match as stored in the database
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MatchRecord {
+    pub id: u32,
+    pub home_id: u32,
+    pub home: String,
+    pub guest_id: u32,
+    pub guest: String,
+    pub won: MatchOutcome,
+    pub timestamp: String,
+    /// side the home image was displayed on, if known
+    pub home_side: Option<Side>,
+    /// time the annotator needed for the decision, if known
+    pub response_ms: Option<u32>,
+    pub annotator: Option<String>,
+    pub session_id: Option<i64>,
+}
+
+/// Gold pair answers and matches of an annotator
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AnnotatorStats {
+    pub annotator: String,
+    /// number of recorded matches, without gold pairs
+    pub matches: u64,
+    pub gold_answers: u64,
+    pub gold_correct: u64,
+    /// share of correct gold answers, None without gold answers
+    pub accuracy: Option<f64>,
+    /// weight of the results in the rating updates
+    pub weight: f64,
+    /// configured weight of a game of the annotator in the rating updates
+    pub game_weight: f64,
+    /// judgements of pairs, which the annotator judged before
+    pub repeated_judgements: u64,
+    /// share of the repeated judgements which agree with the previous judgement of the pair
+    pub self_consistency: Option<f64>,
+    /// triads of images, whose preferences of the annotator form a cycle like a > b > c > a
+    pub intransitive_cycles: u64,
+}
+
+/// Number of matches played in the hour starting at `hour`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Activity {
+    /// in the form of "%Y-%m-%d %H:00" (UTC)
+    pub hour: String,
+    pub matches: u64,
+}
+
+/// Number of players with a deviation in `[lower, upper)`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct DeviationBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub players: u64,
 }
 
+/// Statistics about the played matches and the precision of the ratings
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CampaignStats {
+    pub matches: u64,
+    /// average number of matches per hour between the first and the last match
+    pub matches_per_hour: f64,
+    pub matches_last_hour: u64,
+    /// share of the matches which ended in a draw
+    pub draw_rate: f64,
+    /// histogram of the player deviations with a bucket size of 50
+    pub deviations: Vec<DeviationBucket>,
+    pub min_games: u64,
+    /// number of players with less than `min_games` games
+    pub players_below_min_games: u64,
+    pub target_deviation: Option<f64>,
+    /// estimated number of games until all players reach the target deviation
+    pub remaining_games: Option<u64>,
+    /// estimated position and order bias of the annotators
+    pub position_bias: PositionBias,
+    /// share of the decided matches won by the image displayed on the left.
+    /// Far away from 0.5 indicates a position bias. None without matches with a known side.
+    pub left_win_rate: Option<f64>,
+    /// number of pairs the annotators could not judge, not part of `matches`
+    pub skipped: u64,
+    /// number of players flagged for review, they are excluded from the matchmaking and the statistics
+    pub flagged_players: u64,
+}
+
+/// Error returned by [`ImageCollection::new_duel`] when the stopping criterion is reached
+#[derive(Debug)]
+pub struct CampaignComplete;
+
+impl std::fmt::Display for CampaignComplete {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "campaign is complete")
+    }
+}
+
+impl std::error::Error for CampaignComplete {}
+
 /// a new match which needs to be played
 #[derive(Serialize, Deserialize)]
 pub struct Duel {
@@ -44,29 +489,76 @@ pub struct Duel {
 }
 
 /// The result of a played match in terms of the home player.
+/// Besides win, draw and loss, the graded scale knows slight wins and losses,
+/// which count as fractional scores.
+/// Skipped and flagging matches are recorded, but do not change the ratings.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MatchOutcome {
+    /// home is (much) better
     HomeWin,
+    /// home is slightly better
+    HomeSlightlyBetter,
     Draw,
+    /// guest is slightly better
+    GuestSlightlyBetter,
+    /// guest is (much) better
     GuestWin,
+    /// the annotator could not judge the pair
+    Skip,
+    /// the home image is broken or does not belong to the collection
+    FlagHome,
+    /// the guest image is broken or does not belong to the collection
+    FlagGuest,
 }
 
-impl From<MatchOutcome> for f32 {
-    fn from(o: MatchOutcome) -> f32 {
-        match o {
-            MatchOutcome::HomeWin => 1.0,
-            MatchOutcome::Draw => 0.5,
-            MatchOutcome::GuestWin => 0.0,
+impl MatchOutcome {
+    /// score of the home player, None if the match does not count for the ratings
+    pub fn score(self) -> Option<f64> {
+        match self {
+            MatchOutcome::HomeWin => Some(1.0),
+            MatchOutcome::HomeSlightlyBetter => Some(0.75),
+            MatchOutcome::Draw => Some(0.5),
+            MatchOutcome::GuestSlightlyBetter => Some(0.25),
+            MatchOutcome::GuestWin => Some(0.0),
+            MatchOutcome::Skip | MatchOutcome::FlagHome | MatchOutcome::FlagGuest => None,
+        }
+    }
+
+    /// name of the outcomes without a score, as used in json and the database
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            MatchOutcome::Skip => Some("skip"),
+            MatchOutcome::FlagHome => Some("flag_home"),
+            MatchOutcome::FlagGuest => Some("flag_guest"),
+            _ => None,
+        }
+    }
+
+    fn from_label(label: &str) -> Option<MatchOutcome> {
+        match label {
+            "skip" => Some(MatchOutcome::Skip),
+            "flag_home" => Some(MatchOutcome::FlagHome),
+            "flag_guest" => Some(MatchOutcome::FlagGuest),
+            _ => None,
+        }
+    }
+
+    /// reads the outcome of a stored match, where either the result or the outcome label is set
+    fn from_row(result: Option<f64>, outcome: Option<&str>) -> Result<MatchOutcome> {
+        match (result, outcome) {
+            (Some(result), _) => MatchOutcome::try_from(result as f32).map_err(anyhow::Error::msg),
+            (None, Some(label)) => MatchOutcome::from_label(label)
+                .ok_or_else(|| anyhow::anyhow!("unknown match outcome \"{label}\"")),
+            (None, None) => Err(anyhow::anyhow!("match without result")),
         }
     }
 }
 
-impl From<MatchOutcome> for f64 {
-    fn from(o: MatchOutcome) -> f64 {
-        match o {
-            MatchOutcome::HomeWin => 1.0,
-            MatchOutcome::Draw => 0.5,
-            MatchOutcome::GuestWin => 0.0,
+impl std::fmt::Display for MatchOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.label() {
+            Some(label) => write!(f, "{label}"),
+            None => write!(f, "{}", self.score().unwrap_or_default()),
         }
     }
 }
@@ -76,23 +568,30 @@ impl TryFrom<f32> for MatchOutcome {
     fn try_from(v: f32) -> Result<Self, Self::Error> {
         if v == 1.0 {
             Ok(MatchOutcome::HomeWin)
+        } else if v == 0.75 {
+            Ok(MatchOutcome::HomeSlightlyBetter)
         } else if v == 0.5 {
             Ok(MatchOutcome::Draw)
+        } else if v == 0.25 {
+            Ok(MatchOutcome::GuestSlightlyBetter)
         } else if v == 0.0 {
             Ok(MatchOutcome::GuestWin)
         } else {
-            Err("match outcome must be 0.0, 0.5, or 1.0")
+            Err("match outcome must be 0.0, 0.25, 0.5, 0.75 or 1.0")
         }
     }
 }
 
+/// scores are serialized as numbers, the other outcomes by their label
 impl Serialize for MatchOutcome {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let v: f32 = (*self).into();
-        v.serialize(serializer)
+        match self.label() {
+            Some(label) => label.serialize(serializer),
+            None => (self.score().unwrap_or_default() as f32).serialize(serializer),
+        }
     }
 }
 
@@ -101,22 +600,176 @@ impl<'de> Deserialize<'de> for MatchOutcome {
     where
         D: serde::Deserializer<'de>,
     {
-        let v = f32::deserialize(deserializer)?;
-        Self::try_from(v).map_err(serde::de::Error::custom)
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Score(f32),
+            Label(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Score(v) => Self::try_from(v).map_err(serde::de::Error::custom),
+            Raw::Label(label) => Self::from_label(&label).ok_or_else(|| {
+                serde::de::Error::custom(
+                    "match outcome must be a score or \"skip\", \"flag_home\" or \"flag_guest\"",
+                )
+            }),
+        }
+    }
+}
+
+/// Side of the screen an image was displayed on
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Side::Left => "left",
+            Side::Right => "right",
+        }
+    }
+}
+
+impl std::fmt::Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Side {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Side::Left),
+            "right" => Ok(Side::Right),
+            _ => Err("side must be \"left\" or \"right\""),
+        }
+    }
+}
+
+/// Why an image was reported
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagReason {
+    /// the file is broken or can not be displayed
+    Corrupt,
+    /// the image shows a different product or is labeled wrong
+    Mislabeled,
+    /// the image does not belong to the collection
+    OutOfScope,
+    Other,
+}
+
+impl FlagReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlagReason::Corrupt => "corrupt",
+            FlagReason::Mislabeled => "mislabeled",
+            FlagReason::OutOfScope => "out_of_scope",
+            FlagReason::Other => "other",
+        }
+    }
+}
+
+impl FromStr for FlagReason {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "corrupt" => Ok(FlagReason::Corrupt),
+            "mislabeled" => Ok(FlagReason::Mislabeled),
+            "out_of_scope" => Ok(FlagReason::OutOfScope),
+            "other" => Ok(FlagReason::Other),
+            _ => Err("reason must be \"corrupt\", \"mislabeled\", \"out_of_scope\" or \"other\""),
+        }
+    }
+}
+
+/// Review state of a flag
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagStatus {
+    /// waiting for a review, the image is excluded from the matchmaking
+    Open,
+    /// the image was fine and takes part in the matchmaking again
+    Cleared,
+    /// the image was removed from the matchmaking for good
+    Removed,
+}
+
+impl FlagStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlagStatus::Open => "open",
+            FlagStatus::Cleared => "cleared",
+            FlagStatus::Removed => "removed",
+        }
     }
 }
 
+impl FromStr for FlagStatus {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(FlagStatus::Open),
+            "cleared" => Ok(FlagStatus::Cleared),
+            "removed" => Ok(FlagStatus::Removed),
+            _ => Err("status must be \"open\", \"cleared\" or \"removed\""),
+        }
+    }
+}
+
+/// A report of an image as stored in the database
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FlagRecord {
+    pub id: u32,
+    pub player_id: u32,
+    pub player: String,
+    pub reason: FlagReason,
+    pub comment: Option<String>,
+    pub timestamp: String,
+    pub status: FlagStatus,
+}
+
 /// a played match with the given result
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Match {
     pub home_id: u32,
     pub guest_id: u32,
-    /// 0 if home lost, 0.5 on draw, 1 if home won
+    /// 0 if home lost, 0.5 on draw, 1 if home won, or an unrated outcome like "skip"
     pub won: MatchOutcome,
+    /// side the home image was displayed on, if reported by the client
+    #[serde(default)]
+    pub home_side: Option<Side>,
+    /// time the annotator needed for the decision, if reported by the client
+    #[serde(default)]
+    pub response_ms: Option<u32>,
+    /// id of the annotator, if reported by the client
+    #[serde(default)]
+    pub annotator: Option<String>,
+    /// session of the annotator, assigned by the server
+    #[serde(skip)]
+    pub session_id: Option<i64>,
+}
+
+impl Match {
+    /// the player flagged for review by this match
+    pub fn flagged_player(&self) -> Option<u32> {
+        match self.won {
+            MatchOutcome::FlagHome => Some(self.home_id),
+            MatchOutcome::FlagGuest => Some(self.guest_id),
+            _ => None,
+        }
+    }
 }
 
 impl ImageCollection {
     pub async fn close(&self) {
+        let _backup = self.backup_running.lock().await;
+        self.reader.close().await;
         self.db.close().await //async, can therefore not implemented in drop
     }
 
@@ -124,29 +777,9 @@ impl ImageCollection {
         options: &ImageCollectionOptions,
         image_dir: &String,
     ) -> Result<ImageCollection> {
-        let db_opions = sqlx::sqlite::SqliteConnectOptions::from_str(&options.db_path)?
-            .shared_cache(false)
-            .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
-            .busy_timeout(std::time::Duration::from_secs(5000))
-            .locking_mode(sqlx::sqlite::SqliteLockingMode::Exclusive)
-            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-            .foreign_keys(true)
-            .pragma("temp_store", "MEMORY")
-            .pragma("mmap_size", "134217728")
-            .pragma("journal_size_limit", "67108864")
-            .pragma("cache_size", "64000")
-            .create_if_missing(true);
-
-        let db = sqlx::sqlite::SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect_with(db_opions)
-            .await?;
-        sqlx::query_file!("./schema.sql").execute(&db).await?;
+        let (db, reader) = Db::connect(&options.db_path, options.read_connections).await?;
         check_db_integrity(&db, image_dir).await?;
-        let (max_players,): (i64,) = sqlx::query_as("SELECT COUNT(*) as count FROM players")
-            .fetch_one(&db)
-            .await?;
-        let max_players = max_players as usize;
+        let max_players = db.count_players().await? as usize;
 
         let candidate_buffer = {
             if max_players > options.candidate_buffer {
@@ -165,124 +798,290 @@ impl ImageCollection {
 
         let candidates = ArrayQueue::<Duel>::new(std::cmp::max(1, candidate_buffer));
         let candidates = std::sync::Arc::new(candidates);
-        let new_duels = calculate_new_matches(&db, candidates.capacity()).await?;
+        let new_duels = db
+            .candidates(candidates.capacity(), options.matchmaking)
+            .await?;
         for nd in new_duels.into_iter() {
             let _ = candidates.push(nd);
         }
         let db_update_in_progress = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let status = campaign_status(&db, &options.stopping).await?;
+        if status.complete {
+            info!("Campaign is already complete.");
+        }
+        let complete = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(status.complete));
+        let gold = resolve_gold_pairs(&db, &options.gold_pairs).await?;
+        if !gold.is_empty() {
+            info!("Interleave {} gold pairs.", gold.len());
+        }
+        let bias = if options.correct_position_bias {
+            let bias = estimate_position_bias(&db).await?;
+            info!("Position bias: {:?}", bias);
+            bias
+        } else {
+            PositionBias::default()
+        };
         Ok(ImageCollection {
             candidates,
             db,
+            reader,
             db_update_in_progress,
+            matchmaking: options.matchmaking,
+            stopping: options.stopping,
+            complete,
+            bias: std::sync::Arc::new(std::sync::RwLock::new(bias)),
+            correct_position_bias: options.correct_position_bias,
+            response_time_weighting: options.response_time_weighting,
+            matches_since_bias_estimate: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            matches_since_start: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            gold: std::sync::Arc::new(gold),
+            gold_rate: options.gold_rate,
+            quality_control: options.quality_control,
+            consistency_rate: options.consistency_rate,
+            sessions: options.sessions,
+            backup: options.backup.clone(),
+            backup_running: std::sync::Arc::new(tokio::sync::Mutex::new(())),
         })
     }
 
     pub async fn msre(&self) -> Result<f32> {
-        struct Player {
-            name: String,
-        }
-
-        let players = sqlx::query_as!(Player, "SELECT name FROM players ORDER BY rating")
-            .fetch_all(&self.db)
-            .await?;
-        let mut sqre = 0_f32;
-        let mut len = 0_f32;
-        for (i, rank) in players
-            .iter()
-            .filter_map(|player| player.name.parse::<f32>().ok())
-            .enumerate()
-        {
-            let rankdiff = rank - i as f32;
-            sqre += rankdiff * rankdiff;
-            len += 1_f32;
-        }
-        sqre = (sqre / len).sqrt();
-        Ok(sqre)
+        Ok(msre(&self.reader.players().await?))
     }
 
     pub async fn print_csv(&self) -> Result<()> {
-        struct Player {
-            name: String,
-            rating: f64,
-            deviation: f64,
-        }
-
-        let players = sqlx::query_as!(
-            Player,
-            "SELECT name, rating, deviation FROM players ORDER BY rating"
-        )
-        .fetch_all(&self.db)
-        .await?;
-
-        println! {"original,rating,deviation"};
-
-        for p in players.iter() {
-            println! {"{},{},{}", &p.name, &p.rating, &p.deviation};
-        }
-
+        print_csv(&self.reader.players().await?);
         Ok(())
     }
 
     pub async fn new_pre_configured(num: u32) -> Result<ImageCollection> {
-        let db_opions = sqlx::sqlite::SqliteConnectOptions::from_str(":memory:")?
-            .shared_cache(false)
-            .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
-            .busy_timeout(std::time::Duration::from_secs(3000))
-            .locking_mode(sqlx::sqlite::SqliteLockingMode::Exclusive)
-            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-            .foreign_keys(true)
-            .pragma("temp_store", "MEMORY")
-            .pragma("mmap_size", "134217728")
-            .pragma("journal_size_limit", "67108864")
-            .pragma("cache_size", "64000");
-
-        let db = sqlx::sqlite::SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect_with(db_opions)
-            .await?;
+        Self::new_pre_configured_with(num, &ImageCollectionOptions::default()).await
+    }
 
+    /// creates an in-memory collection with the players 0..num in random order.
+    /// The `db_path` of the options is ignored.
+    pub async fn new_pre_configured_with(
+        num: u32,
+        options: &ImageCollectionOptions,
+    ) -> Result<ImageCollection> {
+        let db = Db::in_memory().await?;
         let db_update_in_progress = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
-        let candidates = std::sync::Arc::new(ArrayQueue::<Duel>::new(20));
-        sqlx::query_file!("./schema.sql").execute(&db).await?;
+        let candidates = std::sync::Arc::new(ArrayQueue::<Duel>::new(std::cmp::max(
+            1,
+            options.candidate_buffer,
+        )));
 
         // generate numbers
         let mut numbers: Vec<u32> = (0..num).collect();
         let mut rng = rand::rng();
         numbers.shuffle(&mut rng);
 
-        let mut tx = db.begin().await?;
+        let mut tx = Tx::begin(&db).await?;
         for i in numbers {
-            sqlx::query!(
-                "
-                INSERT INTO players (name, rating, deviation) 
-                VALUES (?, 2200, 350)
-                ",
-                i
-            )
-            .execute(&mut *tx)
-            .await?;
+            tx.insert_player(&i.to_string()).await?;
         }
         tx.commit().await?;
+        let gold = resolve_gold_pairs(&db, &options.gold_pairs).await?;
 
         Ok(ImageCollection {
             candidates,
+            reader: db.clone(),
             db,
             db_update_in_progress,
+            matchmaking: options.matchmaking,
+            stopping: options.stopping,
+            complete: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            bias: std::sync::Arc::new(std::sync::RwLock::new(PositionBias::default())),
+            correct_position_bias: options.correct_position_bias,
+            response_time_weighting: options.response_time_weighting,
+            matches_since_bias_estimate: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            matches_since_start: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            gold: std::sync::Arc::new(gold),
+            gold_rate: options.gold_rate,
+            quality_control: options.quality_control,
+            consistency_rate: options.consistency_rate,
+            sessions: options.sessions,
+            backup: options.backup.clone(),
+            backup_running: std::sync::Arc::new(tokio::sync::Mutex::new(())),
         })
     }
 
-    /// informs the system about the result of a played match
-    pub async fn insert_match(&self, m: Match) {
+    /// computes the progress of the campaign towards the stopping criterion
+    pub async fn status(&self) -> Result<CampaignStatus> {
+        campaign_status(&self.reader, &self.stopping).await
+    }
+
+    /// computes statistics about the campaign.
+    /// The remaining games are estimated for `target_deviation`,
+    /// or for the deviation of the stopping criterion if not given.
+    pub async fn stats(
+        &self,
+        min_games: u64,
+        target_deviation: Option<f64>,
+    ) -> Result<CampaignStats> {
+        let target_deviation = target_deviation.or(self.stopping.max_deviation);
+        campaign_stats(&self.reader, min_games, target_deviation).await
+    }
+
+    /// returns a page of the players in the given order.
+    /// Players with equal sort keys are ordered by their id.
+    pub async fn players(&self, query: &PlayerQuery) -> Result<PlayerPage> {
+        let total = self.reader.count_players().await?;
+        let players = self
+            .reader
+            .player_page(
+                &query.order_by.to_string(),
+                query.descending,
+                query.limit,
+                query.offset,
+            )
+            .await?
+            .into_iter()
+            .map(PlayerInfo::from)
+            .collect();
+        Ok(PlayerPage { total, players })
+    }
+
+    /// returns the last `limit` played matches, newest first
+    pub async fn recent_matches(&self, limit: u32) -> Result<Vec<MatchRecord>> {
+        self.reader
+            .recent_matches(limit)
+            .await?
+            .into_iter()
+            .map(|m| {
+                Ok(MatchRecord {
+                    id: m.id as u32,
+                    home_id: m.home_players_id as u32,
+                    home: m.home,
+                    guest_id: m.guest_players_id as u32,
+                    guest: m.guest,
+                    won: MatchOutcome::from_row(m.result, m.outcome.as_deref())?,
+                    timestamp: m.timestamp,
+                    home_side: m.home_side.as_deref().and_then(|s| s.parse().ok()),
+                    response_ms: m.response_ms.map(|ms| ms as u32),
+                    annotator: m.annotator,
+                    session_id: m.session_id,
+                })
+            })
+            .collect()
+    }
+
+    /// returns the number of matches per hour of the last `hours` hours.
+    /// Hours without any match are omitted.
+    pub async fn activity(&self, hours: u32) -> Result<Vec<Activity>> {
+        Ok(self
+            .reader
+            .activity(hours)
+            .await?
+            .into_iter()
+            .map(|(hour, matches)| Activity {
+                hour,
+                matches: matches as u64,
+            })
+            .collect())
+    }
+
+    /// true, if the stopping criterion was reached
+    pub fn is_complete(&self) -> bool {
+        self.complete.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    /// informs the system about the result of a played match.
+    /// Returns the session of the annotator, if it is on a break and the match was dropped.
+    pub async fn insert_match(&self, mut m: Match) -> Option<SessionStatus> {
+        if let Some(annotator) = &m.annotator {
+            match join_session(&self.db, &self.sessions, annotator, unix_now()).await {
+                Ok(Ok(id)) => m.session_id = Some(id),
+                Ok(Err(session)) => {
+                    info!(
+                        "Annotator {} is on a break, the match is dropped.",
+                        annotator
+                    );
+                    return Some(session);
+                }
+                Err(err) => error!("Error during recording the session {}", err),
+            }
+        }
+        if let (Some(expected), Some(score)) =
+            (self.gold_result(m.home_id, m.guest_id), m.won.score())
+        {
+            if let Err(err) = record_gold_answer(&self.db, &m, expected, score).await {
+                error!("Error during recording a gold answer {}", err);
+            }
+            return None;
+        }
+        if m.won.score().is_none() {
+            if let Err(err) = self.db.insert_match(&m).await {
+                error!("Error during recording a match {}", err);
+            }
+            if let Some(id) = m.flagged_player() {
+                info!("Player {} flagged for review.", id);
+                self.remove_queued_duels(id);
+            }
+            return None;
+        }
         let db = self.db.clone();
         let can_queue = self.candidates.clone();
         let db_update_in_progress = self.db_update_in_progress.clone();
+        let matchmaking = self.matchmaking;
+        let stopping = self.stopping;
+        let complete = self.complete.clone();
+        let bias = self.bias.clone();
+        let correct_position_bias = self.correct_position_bias;
+        let response_time_weighting = self.response_time_weighting;
+        let quality_control = self.quality_control;
+        let matches_since_bias_estimate = self.matches_since_bias_estimate.clone();
+        let matches_since_start = self.matches_since_start.clone();
         tokio::spawn(async move {
             let now = std::time::Instant::now();
-            match update_rating(&db, &m).await {
+            let model = RatingModel {
+                bias: *bias.read().unwrap_or_else(|e| e.into_inner()),
+                response_time: response_time_weighting,
+                quality_control,
+            };
+            match db.update_rating(&m, &model).await {
                 Err(err) => error!("Error during updating ratings {}", err),
                 Ok(_) => info!("Insert update done in {}ms", now.elapsed().as_millis()),
             };
 
+            if correct_position_bias
+                && matches_since_bias_estimate.fetch_add(1, std::sync::atomic::Ordering::AcqRel) + 1
+                    >= BIAS_ESTIMATE_INTERVAL
+            {
+                matches_since_bias_estimate.store(0, std::sync::atomic::Ordering::Release);
+                match estimate_position_bias(&db).await {
+                    Ok(new_bias) => {
+                        info!("Position bias: {:?}", new_bias);
+                        *bias.write().unwrap_or_else(|e| e.into_inner()) = new_bias;
+                    }
+                    Err(err) => error!("Error during estimating the position bias {}", err),
+                }
+            }
+
+            if stopping.is_set() {
+                let due = matches_since_start
+                    .fetch_add(1, std::sync::atomic::Ordering::AcqRel)
+                    .is_multiple_of(STATUS_CHECK_INTERVAL);
+                // the rank error is quadratic in the players, only the game budget is checked per match
+                let budget_spent = match stopping.max_games {
+                    Some(max_games) => db
+                        .count_rated_matches()
+                        .await
+                        .is_ok_and(|games| games >= max_games),
+                    None => false,
+                };
+                if due || budget_spent {
+                    match campaign_status(&db, &stopping).await {
+                        Ok(status) if status.complete => {
+                            if !complete.swap(true, std::sync::atomic::Ordering::AcqRel) {
+                                info!("Campaign complete after {} games.", status.games);
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(err) => error!("Error during evaluating stopping criterion {}", err),
+                    }
+                }
+            }
+
             if can_queue.len() < (can_queue.capacity() / 2)
                 && db_update_in_progress
                     .compare_exchange(
@@ -295,7 +1094,7 @@ impl ImageCollection {
             {
                 info!("refresh duel queue");
                 let now = std::time::Instant::now();
-                if let Ok(new_duels) = calculate_new_matches(&db, can_queue.capacity()).await {
+                if let Ok(new_duels) = db.candidates(can_queue.capacity(), matchmaking).await {
                     // ignore if queue is full
                     for nd in new_duels.into_iter().skip(can_queue.len() + 1) {
                         let _ = can_queue.push(nd); // ignore output
@@ -309,235 +1108,735 @@ impl ImageCollection {
                 db_update_in_progress.store(false, std::sync::atomic::Ordering::Release);
             }
         });
+        None
     }
 
-    /// requests a new duel which needs to be played
-    pub async fn new_duel(&self) -> Result<Duel> {
-        match self.candidates.pop() {
-            Some(duel) => Ok(duel),
-            _ => {
-                warn!(
-                    "No duels in queue. Manually compute one. Try to increase the size of candidate queue."
-                );
-                let duels = calculate_new_matches(&self.db, 3).await?;
-                duels
-                    .into_iter()
-                    .nth(0)
-                    .ok_or(anyhow::anyhow!("No candidates found"))
-            }
+    /// reports an image for review. It is excluded from the matchmaking until the flag is cleared.
+    /// Returns false if there is no player with this id.
+    pub async fn flag_player(
+        &self,
+        id: u32,
+        reason: FlagReason,
+        comment: Option<&str>,
+    ) -> Result<bool> {
+        let mut tx = Tx::begin(&self.db).await?;
+        if !tx.player_exists(id).await? {
+            return Ok(false);
         }
+        tx.insert_flag(id, reason, comment).await?;
+        tx.commit().await?;
+        info!("Player {} flagged for review.", id);
+        self.remove_queued_duels(id);
+        Ok(true)
     }
-}
-
-async fn check_db_integrity(db: &SqlitePool, image_dir: &String) -> Result<()> {
-    let db_files = sqlx::query!("SELECT name FROM players")
-        .fetch_all(db)
-        .await?;
-    let db_files: std::collections::HashSet<String> =
-        db_files.into_iter().map(|r| r.name).collect();
 
-    let mut tx = db.begin().await?;
+    /// returns the flags with the given status, or all flags, newest first
+    pub async fn flags(&self, status: Option<FlagStatus>) -> Result<Vec<FlagRecord>> {
+        self.reader
+            .flags(status.map(|s| s.as_str()))
+            .await?
+            .into_iter()
+            .map(|f| {
+                Ok(FlagRecord {
+                    id: f.id as u32,
+                    player_id: f.players_id as u32,
+                    player: f.name,
+                    reason: f.reason.parse().map_err(anyhow::Error::msg)?,
+                    comment: f.comment,
+                    timestamp: f.timestamp,
+                    status: f.status.parse().map_err(anyhow::Error::msg)?,
+                })
+            })
+            .collect()
+    }
 
-    // check if all files in db exists in fs
-    for file in &db_files {
-        let file_path = format!("{image_dir}/{file}");
-        if !std::path::Path::new(&file_path).is_file() {
-            info!("Image path \"{}\" does not exists in ", file);
-            sqlx::query!("DELETE FROM players WHERE name = ?", file)
-                .execute(&mut *tx)
-                .await?;
+    /// resolves the flags of a player. Cleared players take part in the matchmaking again,
+    /// removed players stay excluded. Returns false if the player is not flagged.
+    pub async fn review_player(&self, id: u32, status: FlagStatus) -> Result<bool> {
+        if status == FlagStatus::Open {
+            bail!("a review must clear or remove the player");
         }
+        let flagged = status == FlagStatus::Removed;
+        let status = status.as_str();
+        let mut tx = Tx::begin(&self.db).await?;
+        if !tx.review_player(id, flagged, status).await? {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        info!("Player {} reviewed: {}.", id, status);
+        Ok(true)
     }
 
-    for entry in std::fs::read_dir(image_dir)? {
-        if let Some(file) = entry?.file_name().to_str()
-            && !db_files.contains(file)
-        {
-            info!("Add \"{}\" to database.", file);
-            sqlx::query!(
-                "
-                    INSERT INTO players (name, rating, deviation) 
-                    VALUES (?, 2200, 350)
-                    ",
-                file
-            )
-            .execute(&mut *tx)
-            .await?;
-        }
+    /// expected score of the home player, if the players form a gold pair.
+    /// Gold pairs are only served as gold checks, so their answers are never regular matches.
+    fn gold_result(&self, home_id: u32, guest_id: u32) -> Option<f64> {
+        self.gold.iter().find_map(|g| {
+            if g.home_id == home_id && g.guest_id == guest_id {
+                Some(g.result)
+            } else if g.home_id == guest_id && g.guest_id == home_id {
+                Some(1_f64 - g.result)
+            } else {
+                None
+            }
+        })
     }
-    tx.commit().await?;
-    Ok(())
-}
 
-/// updates rating based on a played match and also inserts the new match
-async fn update_rating(db: &SqlitePool, m: &Match) -> Result<()> {
-    let mut tx = db.begin().await?;
+    /// true, if the duel is not a gold pair
+    fn is_regular(&self, duel: &Duel) -> bool {
+        self.gold_result(duel.home_id, duel.guest_id).is_none()
+    }
 
-    // update the rating
-    #[derive(Debug)]
-    struct Rating {
-        rating: f64,
-        deviation: f64,
+    /// replaces duels by random gold pairs at the gold rate.
+    /// Gold pairs with a flagged image are not served.
+    async fn interleave_gold(&self, duels: &mut [Duel]) -> Result<()> {
+        if self.gold.is_empty() {
+            return Ok(());
+        }
+        let slots: Vec<usize> = {
+            let mut rng = rand::rng();
+            (0..duels.len())
+                .filter(|_| rng.random_bool(self.gold_rate))
+                .collect()
+        };
+        if slots.is_empty() {
+            return Ok(());
+        }
+        let flagged: std::collections::HashSet<u32> = self
+            .db
+            .flagged_players()
+            .await?
+            .into_iter()
+            .map(|id| id as u32)
+            .collect();
+        let gold: Vec<&GoldDuel> = self
+            .gold
+            .iter()
+            .filter(|g| !flagged.contains(&g.home_id) && !flagged.contains(&g.guest_id))
+            .collect();
+        if gold.is_empty() {
+            return Ok(());
+        }
+        let mut rng = rand::rng();
+        for slot in slots {
+            let g = gold[rng.random_range(0..gold.len())];
+            let duel = &mut duels[slot];
+            *duel = Duel {
+                home: g.home.clone(),
+                home_id: g.home_id,
+                guest: g.guest.clone(),
+                guest_id: g.guest_id,
+            };
+            // like the regular duels, the sides are random
+            if rng.random_bool(0.5) {
+                std::mem::swap(&mut duel.home, &mut duel.guest);
+                std::mem::swap(&mut duel.home_id, &mut duel.guest_id);
+            }
+        }
+        Ok(())
     }
 
-    let rt_home = sqlx::query_as!(
-        Rating,
-        "SELECT rating, deviation FROM players WHERE id = ?",
-        m.home_id
-    )
-    .fetch_one(&mut *tx)
-    .await?;
-    let rt_guest = sqlx::query_as!(
-        Rating,
-        "SELECT rating, deviation FROM players WHERE id = ?",
-        m.guest_id
-    )
-    .fetch_one(&mut *tx)
-    .await?;
+    /// replaces duels by consistency checks at the consistency rate.
+    /// Half of the checks repeat a pair judged by the annotator, the other half close a triad
+    /// of pairs judged by the annotator. Unknown annotators get no checks.
+    async fn interleave_consistency_checks(
+        &self,
+        duels: &mut [Duel],
+        annotator: Option<&str>,
+    ) -> Result<()> {
+        let Some(annotator) = annotator else {
+            return Ok(());
+        };
+        for duel in duels.iter_mut() {
+            let (check, triad) = {
+                let mut rng = rand::rng();
+                (rng.random_bool(self.consistency_rate), rng.random_bool(0.5))
+            };
+            if !check {
+                continue;
+            }
+            let check = match triad {
+                true => match triad_duel(&self.db, annotator).await? {
+                    Some(d) => Some(d),
+                    None => repeated_duel(&self.db, annotator).await?,
+                },
+                false => repeated_duel(&self.db, annotator).await?,
+            };
+            if let Some(mut check) = check.filter(|c| self.is_regular(c)) {
+                if rand::rng().random_bool(0.5) {
+                    std::mem::swap(&mut check.home, &mut check.guest);
+                    std::mem::swap(&mut check.home_id, &mut check.guest_id);
+                }
+                *duel = check;
+            }
+        }
+        Ok(())
+    }
 
-    let rth = glicko::Rating {
-        deviation: rt_home.deviation,
-        rating: rt_home.rating,
-        time: 0,
-    };
-    let rtg = glicko::Rating {
-        deviation: rt_guest.deviation,
-        rating: rt_guest.rating,
-        time: 0,
-    };
-    let won_home = f64::from(m.won);
-    let rth_new = glicko::new_rating(&rth, &rtg, won_home, 0, 0_f64);
-    let rtg_new = glicko::new_rating(&rtg, &rth, 1.0 - won_home, 0, 0_f64);
-    sqlx::query!(
-        "UPDATE players SET rating = ?, deviation = ? WHERE id = ?",
-        rth_new.rating,
-        rth_new.deviation,
-        m.home_id
-    )
-    .execute(&mut *tx)
-    .await?;
-    sqlx::query!(
-        "UPDATE players SET rating = ?, deviation = ? WHERE id = ?",
-        rtg_new.rating,
-        rtg_new.deviation,
-        m.guest_id
-    )
-    .execute(&mut *tx)
-    .await?;
-
-    // insert the new match
-    let result = f64::from(m.won);
-    sqlx::query!(
-        "INSERT INTO matches (home_players_id, guest_players_id, result, timestamp) VALUES (?, ?, ?, strftime('%Y-%m-%d %H:%M','now'))",
-        m.home_id,
-        m.guest_id,
-        result
-    )
-    .execute(&mut *tx)
-    .await?;
+    /// returns the running session of the annotator and whether it has to take a break
+    pub async fn session(&self, annotator: &str) -> Result<SessionStatus> {
+        let mut tx = Tx::begin(&self.db).await?;
+        let latest = latest_session(&mut tx, annotator).await?;
+        Ok(SessionStatus::new(
+            &self.sessions,
+            latest.as_ref(),
+            unix_now(),
+        ))
+    }
 
-    tx.commit().await?;
+    /// returns the matches and gold pair accuracy of all annotators
+    pub async fn annotators(&self) -> Result<Vec<AnnotatorStats>> {
+        let matches = self.reader.matches_per_annotator().await?;
+        let gold = self.reader.gold_answers_per_annotator().await?;
+        let new_stats = |name: &str| AnnotatorStats {
+            annotator: name.to_string(),
+            matches: 0,
+            gold_answers: 0,
+            gold_correct: 0,
+            accuracy: None,
+            weight: 1_f64,
+            game_weight: 1_f64,
+            repeated_judgements: 0,
+            self_consistency: None,
+            intransitive_cycles: 0,
+        };
+        let weights = self.reader.annotator_weights().await?;
+        let mut annotators = std::collections::BTreeMap::new();
+        for (name, weight) in weights {
+            annotators
+                .entry(name.clone())
+                .or_insert_with(|| new_stats(&name))
+                .game_weight = weight;
+        }
+        for (annotator, count) in matches {
+            annotators
+                .entry(annotator.clone())
+                .or_insert_with(|| new_stats(&annotator))
+                .matches = count as u64;
+        }
+        for (annotator, answers, correct) in gold {
+            let a = annotators
+                .entry(annotator.clone())
+                .or_insert_with(|| new_stats(&annotator));
+            a.gold_answers = answers as u64;
+            a.gold_correct = correct as u64;
+            a.accuracy = Some(correct as f64 / answers as f64);
+            if let Some(qc) = &self.quality_control {
+                a.weight = qc.weight(a.gold_answers, a.gold_correct);
+            }
+        }
+
+        let judgements = self.reader.judgements().await?;
+        let mut per_annotator =
+            std::collections::HashMap::<String, Vec<consistency::Judgement>>::new();
+        for (annotator, home, guest, score) in judgements {
+            per_annotator
+                .entry(annotator)
+                .or_default()
+                .push(consistency::Judgement { home, guest, score });
+        }
+        for (annotator, judgements) in per_annotator {
+            let c = consistency::check(&judgements);
+            if let Some(a) = annotators.get_mut(&annotator) {
+                a.repeated_judgements = c.repeated;
+                a.self_consistency =
+                    (c.repeated > 0).then(|| c.agreeing as f64 / c.repeated as f64);
+                a.intransitive_cycles = c.intransitive_cycles;
+            }
+        }
+        Ok(annotators.into_values().collect())
+    }
+
+    /// sets the weight of the games of the annotator in the rating updates.
+    /// A weight of 2 counts a game like two games, 0 ignores the games.
+    pub async fn set_annotator_weight(&self, annotator: &str, weight: f64) -> Result<()> {
+        if !weight.is_finite() || weight < 0_f64 {
+            bail!("weight must be a non-negative number");
+        }
+        self.db.set_annotator_weight(annotator, weight).await
+    }
+
+    /// drops the queued duels of the given player
+    fn remove_queued_duels(&self, id: u32) {
+        let queued: Vec<Duel> = std::iter::from_fn(|| self.candidates.pop()).collect();
+        for duel in queued {
+            if duel.home_id != id && duel.guest_id != id {
+                let _ = self.candidates.push(duel);
+            }
+        }
+    }
+
+    /// rates the imported judgements in their order, like the judgements of the annotators.
+    /// All images are resolved before the first judgement is stored.
+    /// Gold pairs are skipped, they only check the annotators and are never rated.
+    /// Returns the number of imported matches.
+    pub async fn import_matches(&self, matches: &[ImportedMatch]) -> Result<usize> {
+        let mut resolved = Vec::with_capacity(matches.len());
+        for m in matches {
+            let home_id = self.db.player_id(&m.home).await?;
+            let guest_id = self.db.player_id(&m.guest).await?;
+            let (Some(home_id), Some(guest_id)) = (home_id, guest_id) else {
+                bail!(
+                    "unknown image in the match \"{}\" - \"{}\"",
+                    m.home,
+                    m.guest
+                );
+            };
+            if self.gold_result(home_id as u32, guest_id as u32).is_some() {
+                continue;
+            }
+            let judgement = Match {
+                home_id: home_id as u32,
+                guest_id: guest_id as u32,
+                won: m.result,
+                home_side: None,
+                response_ms: None,
+                annotator: m.annotator.clone(),
+                session_id: None,
+            };
+            resolved.push((judgement, m.timestamp.as_deref()));
+        }
+        if resolved.len() < matches.len() {
+            warn!(
+                "Skipped {} judgements of gold pairs.",
+                matches.len() - resolved.len()
+            );
+        }
+        let model = RatingModel {
+            bias: *self.bias.read().unwrap_or_else(|e| e.into_inner()),
+            response_time: self.response_time_weighting,
+            quality_control: self.quality_control,
+        };
+        // all or nothing, a failed import can be repeated after fixing the file
+        let mut tx = Tx::begin(&self.db).await?;
+        for (m, timestamp) in &resolved {
+            update_rating_at(&mut tx, m, &model, *timestamp).await?;
+        }
+        tx.commit().await?;
+        info!("Imported {} matches.", resolved.len());
+        Ok(resolved.len())
+    }
+
+    /// true, if a backup directory is configured
+    pub fn backups_enabled(&self) -> bool {
+        self.backup.is_some()
+    }
+
+    /// writes a consistent snapshot of the database into the backup directory
+    /// and deletes the oldest snapshots beyond the configured number
+    pub async fn backup(&self) -> Result<Backup> {
+        let Some(options) = &self.backup else {
+            bail!("no backup directory configured");
+        };
+        let reader = self.reader.clone();
+        let options = options.clone();
+        let running = self.backup_running.clone();
+        // a cancelled request does not abort the snapshot, it is completed or cleaned up
+        tokio::spawn(async move {
+            let _running = running.lock().await;
+            backup::backup(&reader, &options).await
+        })
+        .await?
+    }
+
+    /// requests a new duel which needs to be played.
+    /// Fails with [`CampaignComplete`] when the stopping criterion is reached.
+    pub async fn new_duel(&self) -> Result<Duel> {
+        let duels = self.new_duels(1, None).await?;
+        duels
+            .into_iter()
+            .nth(0)
+            .ok_or(anyhow::anyhow!("No candidates found"))
+    }
+
+    /// requests the next `count` duels, so clients can preload the upcoming images.
+    /// Duels are taken from the candidate queue, missing ones get computed.
+    /// The consistency checks of the `annotator` are taken from its own matches.
+    /// Fails with [`CampaignComplete`] when the stopping criterion is reached.
+    pub async fn new_duels(&self, count: usize, annotator: Option<&str>) -> Result<Vec<Duel>> {
+        if self.is_complete() {
+            return Err(CampaignComplete.into());
+        }
+        // gold pairs from the matchmaking are skipped, they are only served as gold checks
+        let mut duels: Vec<Duel> = std::iter::from_fn(|| self.candidates.pop())
+            .filter(|d| self.is_regular(d))
+            .take(count)
+            .collect();
+        if duels.len() < count {
+            warn!(
+                "Not enough duels in queue. Manually compute {}. Try to increase the size of candidate queue.",
+                count - duels.len()
+            );
+            let missing = count - duels.len();
+            let computed = self
+                .db
+                .candidates(std::cmp::max(3, missing), self.matchmaking)
+                .await?;
+            duels.extend(
+                computed
+                    .into_iter()
+                    .filter(|d| self.is_regular(d))
+                    .take(missing),
+            );
+        }
+        if duels.is_empty() && count > 0 {
+            return Err(anyhow::anyhow!("No candidates found"));
+        }
+        self.interleave_consistency_checks(&mut duels, annotator)
+            .await?;
+        self.interleave_gold(&mut duels).await?;
+        Ok(duels)
+    }
+}
 
+async fn check_db_integrity(db: &Db, image_dir: &String) -> Result<()> {
+    let db_files: std::collections::HashSet<String> =
+        db.player_names().await?.into_iter().collect();
+
+    let mut tx = Tx::begin(db).await?;
+    // check if all files in db exists in fs
+    for file in &db_files {
+        let file_path = format!("{image_dir}/{file}");
+        if !std::path::Path::new(&file_path).is_file() {
+            info!("Image path \"{}\" does not exists in ", file);
+            tx.delete_player(file).await?;
+        }
+    }
+
+    for entry in std::fs::read_dir(image_dir)? {
+        if let Some(file) = entry?.file_name().to_str()
+            && !db_files.contains(file)
+        {
+            info!("Add \"{}\" to database.", file);
+            tx.insert_player(file).await?;
+        }
+    }
+    tx.commit().await?;
     Ok(())
 }
 
-struct Player {
-    id: i64,
-    rating: f64,
-    deviation: f64,
-    name: String,
+async fn campaign_status(db: &Db, stopping: &StoppingCriterion) -> Result<CampaignStatus> {
+    // flagged players are not rated anymore and would prevent the campaign from completing
+    let ratings = db.unflagged_ratings().await?;
+    let games = db.count_rated_matches().await?;
+
+    let players = ratings.len() as u64;
+    let max_deviation = ratings.iter().map(|r| r.1).fold(0_f64, f64::max);
+    let rank_error = convergence::rank_error(&ratings);
+
+    // progress is relative to unrated players, which all have the same rating
+    let initial_rank_error = convergence::initial_rank_error(ratings.len());
+    let fraction = |initial: f64, current: f64, target: f64| {
+        if initial <= target {
+            1_f64
+        } else {
+            ((initial - current) / (initial - target)).clamp(0_f64, 1_f64)
+        }
+    };
+    let progress = [
+        stopping
+            .max_deviation
+            .map(|t| fraction(350_f64, max_deviation, t)),
+        stopping
+            .max_rank_error
+            .map(|t| fraction(initial_rank_error, rank_error, t)),
+        stopping
+            .max_games
+            .map(|t| fraction(t as f64, t.saturating_sub(games) as f64, 0_f64)),
+    ]
+    .into_iter()
+    .flatten()
+    .reduce(f64::max);
+
+    let complete = stopping.max_deviation.is_some_and(|t| max_deviation < t)
+        || stopping.max_rank_error.is_some_and(|t| rank_error < t)
+        || stopping.max_games.is_some_and(|t| games >= t);
+
+    Ok(CampaignStatus {
+        complete,
+        progress,
+        players,
+        games,
+        max_deviation,
+        rank_error,
+        criterion: *stopping,
+    })
 }
 
-async fn select_random_player_uniform(db: &SqlitePool, home_id: &Player) -> Result<Player> {
-    let all = sqlx::query_scalar!("SELECT COUNT(*) FROM players")
-        .fetch_one(db)
-        .await?;
-    let rnd = {
-        let mut rng = rand::rng();
-        let distr = rand::distr::Uniform::new(0, all - 1)?;
-        rng.sample(distr)
+async fn campaign_stats(
+    db: &Db,
+    min_games: u64,
+    target_deviation: Option<f64>,
+) -> Result<CampaignStats> {
+    let summary = db.match_summary().await?;
+    let players = db.unflagged_players().await?;
+    let flagged_players = db.count_flagged().await?;
+    let games = count_games(judged_pairs(db).await?.into_iter());
+
+    let matches = summary.matches as u64;
+    let draw_rate = if matches > 0 {
+        summary.draws as f64 / matches as f64
+    } else {
+        0_f64
     };
-    let random_id = sqlx::query_as!(
-        Player,
-        "SELECT id, rating, deviation, name FROM players
-        WHERE id != $1 LIMIT 1 OFFSET $2",
-        home_id.id,
-        rnd
-    )
-    .fetch_one(db)
-    .await?;
-    Ok(random_id)
-}
-
-/// always the best performing strategy
-async fn select_random_player(db: &SqlitePool, home_id: &Player) -> Result<Player> {
-    // matchmaking
-    // todo: make some smulations of different matchmakings?
-    let upper = home_id.rating + 0.96 * home_id.deviation;
-    let lower = home_id.rating - 0.96 * home_id.deviation;
-
-    let random_id = sqlx::query_as!(
-        Player,
-        "SELECT id as \"id!\", rating, deviation, name FROM players
-            WHERE id IN (SELECT id FROM players
-                WHERE id != $1 AND
-                rating <= $2 AND
-                rating >= $3
-                LIMIT 1 OFFSET (ABS(RANDOM()) % (SELECT COUNT(*)
-                    FROM players WHERE id != $1 AND
-                    rating <= $2 AND
-                    rating >= $3))
-                )",
-        home_id.id,
-        upper,
-        lower
-    )
-    .fetch_one(db)
-    .await;
+    let left_win_rate =
+        (summary.sided > 0).then(|| summary.left_wins as f64 / summary.sided as f64);
+    // timestamps have a resolution of minutes, avoid huge rates for short campaigns
+    let matches_per_hour = matches as f64 / summary.hours.max(1_f64);
+
+    const BUCKET: f64 = 50_f64;
+    let mut buckets: Vec<DeviationBucket> = (0..7)
+        .map(|i| DeviationBucket {
+            lower: i as f64 * BUCKET,
+            upper: (i + 1) as f64 * BUCKET,
+            players: 0,
+        })
+        .collect();
+    for p in &players {
+        let i = ((p.deviation / BUCKET) as usize).min(buckets.len() - 1);
+        buckets[i].players += 1;
+    }
+
+    let players_below_min_games = players
+        .iter()
+        .filter(|p| (games.get(&p.id).copied().unwrap_or(0) as u64) < min_games)
+        .count() as u64;
+
+    // every game lowers the deviation of two players
+    let remaining_games = target_deviation.map(|target| {
+        let games: f64 = players
+            .iter()
+            .map(|p| glicko::games_to_deviation(p.deviation, target, target))
+            .sum();
+        (games / 2_f64).ceil() as u64
+    });
+
+    Ok(CampaignStats {
+        matches,
+        matches_per_hour,
+        matches_last_hour: summary.last_hour as u64,
+        draw_rate,
+        deviations: buckets,
+        min_games,
+        players_below_min_games,
+        target_deviation,
+        remaining_games,
+        left_win_rate,
+        position_bias: estimate_position_bias(db).await?,
+        skipped: summary.skipped as u64,
+        flagged_players,
+    })
+}
 
-    match random_id {
-        Ok(r) => Ok(r),
-        _ => select_random_player_uniform(db, home_id).await,
+/// root of the mean squared difference between the rank of the players and their name,
+/// for players named after their true rank like in the simulation
+pub fn msre(players: &[PlayerInfo]) -> f32 {
+    let mut players: Vec<&PlayerInfo> = players.iter().collect();
+    players.sort_by(|a, b| a.rating.total_cmp(&b.rating));
+    let mut sqre = 0_f32;
+    let mut len = 0_f32;
+    for (i, rank) in players
+        .iter()
+        .filter_map(|player| player.name.parse::<f32>().ok())
+        .enumerate()
+    {
+        let rankdiff = rank - i as f32;
+        sqre += rankdiff * rankdiff;
+        len += 1_f32;
     }
+    (sqre / len).sqrt()
 }
 
-async fn calculate_new_matches(db: &SqlitePool, n_matches: usize) -> Result<Vec<Duel>> {
-    let n_matches = n_matches as u32;
-    let home_players = sqlx::query_as!(
-        Player,
-        "SELECT id, rating, deviation, name FROM players 
-                ORDER BY deviation DESC 
-                LIMIT ?",
-        n_matches
-    )
-    .fetch_all(db)
-    .await?;
+/// prints the players ordered by their rating as CSV
+pub fn print_csv(players: &[PlayerInfo]) {
+    let mut players: Vec<&PlayerInfo> = players.iter().collect();
+    players.sort_by(|a, b| a.rating.total_cmp(&b.rating));
+
+    println! {"original,rating,deviation"};
+
+    for p in players.iter() {
+        println! {"{},{},{}", &p.name, &p.rating, &p.deviation};
+    }
+}
+
+/// counts the games per player id
+fn count_games(matches: impl Iterator<Item = (i64, i64)>) -> std::collections::HashMap<i64, usize> {
+    let mut games = std::collections::HashMap::<i64, usize>::new();
+    for (home, guest) in matches {
+        *games.entry(home).or_default() += 1;
+        *games.entry(guest).or_default() += 1;
+    }
+    games
+}
+
+/// estimates the position and order bias from all matches with a known side and the current ratings
+async fn estimate_position_bias(db: &Db) -> Result<PositionBias> {
+    let matches = db.bias_rows().await?;
+    let mut observations = Vec::new();
+    // the previous duel is the one of the same annotator, unknown annotators have none
+    let mut previous_winners = std::collections::HashMap::<String, Option<Side>>::new();
+    for m in matches {
+        let home_side: Option<Side> = m.home_side.as_deref().and_then(|s| s.parse().ok());
+        let previous_winner = m
+            .annotator
+            .as_ref()
+            .and_then(|a| previous_winners.get(a).copied().flatten());
+        match (home_side, m.result) {
+            (Some(Side::Left), Some(result)) => observations.push(bias::Observation {
+                rating_diff: m.rating_diff,
+                previous_winner,
+                left_score: result,
+            }),
+            (Some(Side::Right), Some(result)) => observations.push(bias::Observation {
+                rating_diff: -m.rating_diff,
+                previous_winner,
+                left_score: 1_f64 - result,
+            }),
+            _ => {}
+        }
+        // a skipped duel has no winner
+        if let Some(annotator) = m.annotator {
+            previous_winners.insert(
+                annotator,
+                m.result.and_then(|result| winner_side(home_side, result)),
+            );
+        }
+    }
+    Ok(bias::estimate(&observations))
+}
+
+/// side of the winner of a match, None on a draw or if the side is unknown
+fn winner_side(home_side: Option<Side>, result: f64) -> Option<Side> {
+    match home_side {
+        Some(side) if result > 0.5 => Some(side),
+        Some(Side::Left) if result < 0.5 => Some(Side::Right),
+        Some(Side::Right) if result < 0.5 => Some(Side::Left),
+        _ => None,
+    }
+}
+
+/// a random pair, which was judged before by the annotator
+async fn repeated_duel(db: &Db, annotator: &str) -> Result<Option<Duel>> {
+    db.repeated_pair(annotator).await
+}
+
+/// a pair a-c, which the annotator did not judge yet, while it judged a-b and b-c
+async fn triad_duel(db: &Db, annotator: &str) -> Result<Option<Duel>> {
+    let start = db.random_pair(annotator).await?;
+    let Some((a, b)) = start else {
+        return Ok(None);
+    };
+    let opponents = |pairs: Vec<(i64, i64)>, id: i64| -> std::collections::HashSet<i64> {
+        pairs
+            .into_iter()
+            .map(|(home, guest)| if home == id { guest } else { home })
+            .collect()
+    };
+    let opponents_a = opponents(db.pairs_of(annotator, a).await?, a);
+    let candidates: Vec<i64> = opponents(db.pairs_of(annotator, b).await?, b)
+        .into_iter()
+        .filter(|c| *c != a && !opponents_a.contains(c))
+        .collect();
+    let Some(&c) = candidates.choose(&mut rand::rng()) else {
+        return Ok(None);
+    };
+    let players = db.unflagged_names(a, c).await?;
+    let name = |id: i64| players.iter().find(|p| p.0 == id).map(|p| p.1.clone());
+    Ok(match (name(a), name(c)) {
+        (Some(home), Some(guest)) => Some(Duel {
+            home,
+            home_id: a as u32,
+            guest,
+            guest_id: c as u32,
+        }),
+        _ => None,
+    })
+}
 
-    let mut stream = tokio_stream::iter(home_players);
-    let mut result = Vec::new();
-    while let Some(home_id) = stream.next().await {
-        if let Ok(guest) = select_random_player(db, &home_id).await {
-            info!("Selected: {} - {}", home_id.name, guest.name);
-            result.push(Duel {
-                home: home_id.name,
-                home_id: home_id.id as u32,
-                guest: guest.name,
-                guest_id: guest.id as u32,
-            });
+/// looks up the players of the gold pairs. Pairs with unknown images are ignored.
+async fn resolve_gold_pairs(db: &Db, pairs: &[GoldPair]) -> Result<Vec<GoldDuel>> {
+    let mut gold = Vec::new();
+    for pair in pairs {
+        let home = db.player_id(&pair.home).await?;
+        let guest = db.player_id(&pair.guest).await?;
+        match (home, guest, pair.result.score()) {
+            (Some(home_id), Some(guest_id), Some(result)) if home_id != guest_id => {
+                gold.push(GoldDuel {
+                    home: pair.home.clone(),
+                    home_id: home_id as u32,
+                    guest: pair.guest.clone(),
+                    guest_id: guest_id as u32,
+                    result,
+                })
+            }
+            _ => warn!(
+                "Ignore gold pair \"{}\" - \"{}\", the images are unknown.",
+                pair.home, pair.guest
+            ),
         }
     }
-    Ok(result)
+    Ok(gold)
+}
+
+/// stores the answer to a gold pair, it does not change the ratings
+async fn record_gold_answer(db: &Db, m: &Match, expected: f64, score: f64) -> Result<()> {
+    let correct = gold::is_correct(expected, score);
+    db.insert_gold_answer(m, expected, score, correct).await
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+async fn latest_session(tx: &mut Tx, annotator: &str) -> Result<Option<session::Session>> {
+    let latest = tx.latest_session(annotator).await?;
+    Ok(
+        latest.map(|(id, started, last_active, matches)| session::Session {
+            id,
+            started,
+            last_active,
+            matches: matches as u64,
+        }),
+    )
+}
+
+/// counts a judgement of the annotator in its session.
+/// Returns the id of the session, or the session status if the annotator has to take a break.
+async fn join_session(
+    db: &Db,
+    limits: &SessionLimits,
+    annotator: &str,
+    now: i64,
+) -> Result<std::result::Result<i64, SessionStatus>> {
+    let mut tx = Tx::begin(db).await?;
+    let latest = latest_session(&mut tx, annotator).await?;
+    let id = match limits.next(latest.as_ref(), now) {
+        session::Next::Break { .. } => {
+            return Ok(Err(SessionStatus::new(limits, latest.as_ref(), now)));
+        }
+        session::Next::Continue(id) => {
+            tx.continue_session(id, now).await?;
+            id
+        }
+        session::Next::Start => tx.start_session(annotator, now).await?,
+    };
+    tx.commit().await?;
+    Ok(Ok(id))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use storage::{
+        calculate_new_matches, calculate_random_matches, calculate_swiss_matches, update_rating,
+    };
 
     #[tokio::test]
     async fn test_new_pre_configured_creates_correct_number_of_players() {
         let ic = ImageCollection::new_pre_configured(10).await.unwrap();
         let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM players")
-            .fetch_one(&ic.db)
+            .fetch_one(ic.db.sqlite())
             .await
             .unwrap();
         assert_eq!(count.0, 10);
@@ -564,10 +1863,21 @@ mod tests {
         assert!(ic.new_duel().await.is_err());
     }
 
+    #[tokio::test]
+    async fn test_new_duels_returns_batch() {
+        let ic = ImageCollection::new_pre_configured(10).await.unwrap();
+        let duels = ic.new_duels(5, None).await.unwrap();
+        assert_eq!(duels.len(), 5);
+        assert!(duels.iter().all(|d| d.home_id != d.guest_id));
+        assert!(ic.new_duels(0, None).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_calculate_new_matches_returns_duels_up_to_n() {
         let ic = ImageCollection::new_pre_configured(5).await.unwrap();
-        let duels = calculate_new_matches(&ic.db, 3).await.unwrap();
+        let duels = calculate_new_matches(&ic.db, 3, Matchmaking::Random)
+            .await
+            .unwrap();
         assert!(!duels.is_empty());
         assert!(duels.len() <= 3);
         for duel in &duels {
@@ -579,80 +1889,666 @@ mod tests {
     async fn test_calculate_new_matches_orders_home_by_deviation() {
         let ic = ImageCollection::new_pre_configured(3).await.unwrap();
         sqlx::query("UPDATE players SET deviation = 100 WHERE id = 1")
-            .execute(&ic.db)
+            .execute(ic.db.sqlite())
             .await
             .unwrap();
         sqlx::query("UPDATE players SET deviation = 200 WHERE id = 2")
-            .execute(&ic.db)
+            .execute(ic.db.sqlite())
             .await
             .unwrap();
         sqlx::query("UPDATE players SET deviation = 300 WHERE id = 3")
-            .execute(&ic.db)
+            .execute(ic.db.sqlite())
             .await
             .unwrap();
 
-        let duels = calculate_new_matches(&ic.db, 3).await.unwrap();
+        let duels = calculate_random_matches(&ic.db, 3).await.unwrap();
         assert_eq!(duels.len(), 3);
         assert_eq!(duels[0].home_id, 3);
         assert_eq!(duels[1].home_id, 2);
         assert_eq!(duels[2].home_id, 1);
+
+        // the most uncertain player is still matched first, but on a random side
+        let mut home_sides = std::collections::HashSet::new();
+        for _ in 0..50 {
+            let duels = calculate_new_matches(&ic.db, 3, Matchmaking::Random)
+                .await
+                .unwrap();
+            assert!(duels[0].home_id == 3 || duels[0].guest_id == 3);
+            home_sides.insert(duels[0].home_id == 3);
+        }
+        assert_eq!(home_sides.len(), 2);
     }
 
     #[tokio::test]
-    async fn test_update_rating_increases_winner_rating() {
-        let ic = ImageCollection::new_pre_configured(2).await.unwrap();
+    async fn test_swiss_matches_cover_every_player_once_per_round() {
+        let options = ImageCollectionOptions {
+            matchmaking: Matchmaking::Swiss,
+            ..Default::default()
+        };
+        let ic = ImageCollection::new_pre_configured_with(6, &options)
+            .await
+            .unwrap();
+        let duels = calculate_new_matches(&ic.db, 10, Matchmaking::Swiss)
+            .await
+            .unwrap();
+        assert_eq!(duels.len(), 3);
+        let mut seen = std::collections::HashSet::new();
+        for duel in &duels {
+            assert!(seen.insert(duel.home_id));
+            assert!(seen.insert(duel.guest_id));
+        }
+
+        for duel in &duels[..2] {
+            let m = Match {
+                home_id: duel.home_id,
+                guest_id: duel.guest_id,
+                won: MatchOutcome::HomeWin,
+                home_side: None,
+                response_ms: None,
+                annotator: None,
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+        }
+        let next = calculate_new_matches(&ic.db, 1, Matchmaking::Swiss)
+            .await
+            .unwrap();
+        assert_eq!(next.len(), 1);
+        assert_eq!(
+            next[0].home_id.min(next[0].guest_id),
+            duels[2].home_id.min(duels[2].guest_id)
+        );
+        assert_eq!(
+            next[0].home_id.max(next[0].guest_id),
+            duels[2].home_id.max(duels[2].guest_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_without_criterion_never_completes() {
+        let ic = ImageCollection::new_pre_configured(5).await.unwrap();
+        let status = ic.status().await.unwrap();
+        assert!(!status.complete);
+        assert_eq!(status.progress, None);
+        assert_eq!(status.players, 5);
+        assert_eq!(status.games, 0);
+        assert_eq!(status.max_deviation, 350.0);
+        assert!((status.rank_error - 3_f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_status_completes_on_game_budget() {
+        let options = ImageCollectionOptions {
+            stopping: StoppingCriterion {
+                max_games: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let ic = ImageCollection::new_pre_configured_with(5, &options)
+            .await
+            .unwrap();
         let m = Match {
             home_id: 1,
             guest_id: 2,
             won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
         };
-        update_rating(&ic.db, &m).await.unwrap();
+        update_rating(&ic.db, &m, &RatingModel::default())
+            .await
+            .unwrap();
+        let status = ic.status().await.unwrap();
+        assert!(!status.complete);
+        assert_eq!(status.progress, Some(0.5));
 
-        let home: (f64, f64) = sqlx::query_as("SELECT rating, deviation FROM players WHERE id = 1")
-            .fetch_one(&ic.db)
+        update_rating(&ic.db, &m, &RatingModel::default())
             .await
             .unwrap();
-        assert!(home.0 > 2200.0);
-        assert!(home.1 < 350.0);
+        let status = ic.status().await.unwrap();
+        assert!(status.complete);
+        assert_eq!(status.progress, Some(1.0));
+    }
 
-        let guest: (f64, f64) =
-            sqlx::query_as("SELECT rating, deviation FROM players WHERE id = 2")
-                .fetch_one(&ic.db)
+    #[tokio::test]
+    async fn test_status_completes_on_deviation() {
+        let options = ImageCollectionOptions {
+            stopping: StoppingCriterion {
+                max_deviation: Some(100.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let ic = ImageCollection::new_pre_configured_with(3, &options)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE players SET deviation = 50")
+            .execute(ic.db.sqlite())
+            .await
+            .unwrap();
+        let status = ic.status().await.unwrap();
+        assert!(status.complete);
+    }
+
+    #[tokio::test]
+    async fn test_insert_match_reaches_stopping_criterion() {
+        let judge = |won| Match {
+            home_id: 1,
+            guest_id: 2,
+            won,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        };
+        let wait_for_games = |ic: &ImageCollection, games: u64| {
+            let ic = ic.clone();
+            async move {
+                for _ in 0..200 {
+                    if ic.status().await.unwrap().games >= games {
+                        break;
+                    }
+                    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                }
+            }
+        };
+
+        // the deviation is only evaluated every few matches, but the campaign still completes
+        let options = ImageCollectionOptions {
+            stopping: StoppingCriterion {
+                max_deviation: Some(150.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let ic = ImageCollection::new_pre_configured_with(2, &options)
+            .await
+            .unwrap();
+        let mut games = 0;
+        while !ic.is_complete() && games < 10 * STATUS_CHECK_INTERVAL {
+            ic.insert_match(judge(MatchOutcome::Draw)).await;
+            games += 1;
+            wait_for_games(&ic, games).await;
+        }
+        assert!(ic.is_complete());
+        assert!(games > 1);
+
+        // the game budget is checked after every match
+        let options = ImageCollectionOptions {
+            stopping: StoppingCriterion {
+                max_games: Some(3),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let ic = ImageCollection::new_pre_configured_with(2, &options)
+            .await
+            .unwrap();
+        for games in 1..=3 {
+            assert!(!ic.is_complete());
+            ic.insert_match(judge(MatchOutcome::HomeWin)).await;
+            wait_for_games(&ic, games).await;
+        }
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !ic.is_complete() {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("the campaign did not complete");
+    }
+
+    #[tokio::test]
+    async fn test_stats_counts_matches_and_players() {
+        let ic = ImageCollection::new_pre_configured(4).await.unwrap();
+        for won in [MatchOutcome::HomeWin, MatchOutcome::Draw] {
+            let m = Match {
+                home_id: 1,
+                guest_id: 2,
+                won,
+                home_side: None,
+                response_ms: None,
+                annotator: None,
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
                 .await
                 .unwrap();
-        assert!(guest.0 < 2200.0);
-        assert!(guest.1 < 350.0);
+        }
+        // skipped pairs are not counted as matches
+        ic.insert_match(Match {
+            home_id: 3,
+            guest_id: 4,
+            won: MatchOutcome::Skip,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        })
+        .await;
+        let stats = ic.stats(2, Some(100.0)).await.unwrap();
+        assert_eq!(stats.matches, 2);
+        assert_eq!(stats.matches_last_hour, 2);
+        assert_eq!(stats.matches_per_hour, 2.0);
+        assert_eq!(stats.draw_rate, 0.5);
+        assert_eq!(stats.players_below_min_games, 2);
+        assert_eq!(stats.deviations.len(), 7);
+        assert_eq!(stats.deviations.iter().map(|b| b.players).sum::<u64>(), 4);
+        assert_eq!(stats.deviations[6].players, 2);
+        assert!(stats.remaining_games.unwrap() > 0);
+
+        let stats = ic.stats(0, None).await.unwrap();
+        assert_eq!(stats.players_below_min_games, 0);
+        assert_eq!(stats.remaining_games, None);
+    }
 
-        let match_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM matches")
-            .fetch_one(&ic.db)
+    #[tokio::test]
+    async fn test_stats_left_win_rate() {
+        let ic = ImageCollection::new_pre_configured(4).await.unwrap();
+        assert_eq!(ic.stats(0, None).await.unwrap().left_win_rate, None);
+        for (won, home_side) in [
+            (MatchOutcome::HomeWin, Some(Side::Left)),
+            (MatchOutcome::GuestWin, Some(Side::Right)),
+            (MatchOutcome::HomeWin, Some(Side::Right)),
+            // draws and matches without a known side are ignored
+            (MatchOutcome::Draw, Some(Side::Left)),
+            (MatchOutcome::GuestWin, None),
+        ] {
+            let m = Match {
+                home_id: 1,
+                guest_id: 2,
+                won,
+                home_side,
+                response_ms: None,
+                annotator: None,
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+        }
+        let stats = ic.stats(0, None).await.unwrap();
+        assert!((stats.left_win_rate.unwrap() - 2.0 / 3.0).abs() < 1e-9);
+
+        let recent = ic.recent_matches(5).await.unwrap();
+        assert_eq!(recent[0].home_side, None);
+        assert_eq!(recent[1].home_side, Some(Side::Left));
+    }
+
+    #[tokio::test]
+    async fn test_players_recent_matches_and_activity() {
+        let ic = ImageCollection::new_pre_configured(3).await.unwrap();
+        for (home_id, guest_id) in [(1, 2), (1, 3)] {
+            let m = Match {
+                home_id,
+                guest_id,
+                won: MatchOutcome::HomeWin,
+                home_side: None,
+                response_ms: None,
+                annotator: None,
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+        }
+
+        let page = ic.players(&PlayerQuery::default()).await.unwrap();
+        assert_eq!(page.total, 3);
+        let players = page.players;
+        assert_eq!(players.len(), 3);
+        assert_eq!(players[0].id, 1);
+        assert_eq!(players[0].games, 2);
+        assert!(players.windows(2).all(|p| p[0].rating >= p[1].rating));
+        assert_eq!(players.iter().map(|p| p.games).sum::<u64>(), 4);
+
+        let matches = ic.recent_matches(1).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].home_id, 1);
+        assert_eq!(matches[0].guest_id, 3);
+        assert_eq!(matches[0].won, MatchOutcome::HomeWin);
+
+        let activity = ic.activity(24).await.unwrap();
+        assert_eq!(activity.len(), 1);
+        assert_eq!(activity[0].matches, 2);
+        assert!(activity[0].hour.ends_with(":00"));
+    }
+
+    #[tokio::test]
+    async fn test_players_sorting_and_paging() {
+        let ic = ImageCollection::new_pre_configured(4).await.unwrap();
+        for (id, deviation) in [(1, 300), (2, 100), (3, 200), (4, 50)] {
+            sqlx::query("UPDATE players SET deviation = ? WHERE id = ?")
+                .bind(deviation)
+                .bind(id)
+                .execute(ic.db.sqlite())
+                .await
+                .unwrap();
+        }
+        let m = Match {
+            home_id: 3,
+            guest_id: 4,
+            won: MatchOutcome::Draw,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        };
+        update_rating(&ic.db, &m, &RatingModel::default())
             .await
             .unwrap();
-        assert_eq!(match_count.0, 1);
+
+        let query = PlayerQuery {
+            order_by: PlayerOrder::Deviation,
+            descending: false,
+            offset: 0,
+            limit: 2,
+        };
+        let page = ic.players(&query).await.unwrap();
+        assert_eq!(page.total, 4);
+        let ids: Vec<u32> = page.players.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![4, 2]);
+
+        let page = ic
+            .players(&PlayerQuery { offset: 2, ..query })
+            .await
+            .unwrap();
+        let ids: Vec<u32> = page.players.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![3, 1]);
+
+        let query = PlayerQuery {
+            order_by: PlayerOrder::Games,
+            descending: true,
+            offset: 0,
+            limit: 10,
+        };
+        let page = ic.players(&query).await.unwrap();
+        let ids: Vec<u32> = page.players.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![3, 4, 1, 2]);
     }
 
     #[tokio::test]
-    async fn test_draw_does_not_change_ratings_significantly() {
+    async fn test_new_duel_fails_when_complete() {
+        let ic = ImageCollection::new_pre_configured(5).await.unwrap();
+        ic.complete
+            .store(true, std::sync::atomic::Ordering::Release);
+        let err = ic.new_duel().await.err().unwrap();
+        assert!(err.is::<CampaignComplete>());
+    }
+
+    #[test]
+    fn test_matchmaking_from_str() {
+        assert_eq!("random".parse::<Matchmaking>(), Ok(Matchmaking::Random));
+        assert_eq!("swiss".parse::<Matchmaking>(), Ok(Matchmaking::Swiss));
+        assert!("elo".parse::<Matchmaking>().is_err());
+        assert_eq!(Matchmaking::Swiss.to_string(), "swiss");
+    }
+
+    #[tokio::test]
+    async fn test_update_rating_increases_winner_rating() {
         let ic = ImageCollection::new_pre_configured(2).await.unwrap();
         let m = Match {
             home_id: 1,
             guest_id: 2,
-            won: MatchOutcome::Draw,
+            won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
         };
-        update_rating(&ic.db, &m).await.unwrap();
+        update_rating(&ic.db, &m, &RatingModel::default())
+            .await
+            .unwrap();
 
         let home: (f64, f64) = sqlx::query_as("SELECT rating, deviation FROM players WHERE id = 1")
-            .fetch_one(&ic.db)
+            .fetch_one(ic.db.sqlite())
             .await
             .unwrap();
-        assert!((home.0 - 2200.0).abs() < 50.0);
+        assert!(home.0 > 2200.0);
         assert!(home.1 < 350.0);
 
-        let match_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM matches")
-            .fetch_one(&ic.db)
-            .await
-            .unwrap();
-        assert_eq!(match_count.0, 1);
-    }
+        let guest: (f64, f64) =
+            sqlx::query_as("SELECT rating, deviation FROM players WHERE id = 2")
+                .fetch_one(ic.db.sqlite())
+                .await
+                .unwrap();
+        assert!(guest.0 < 2200.0);
+        assert!(guest.1 < 350.0);
+
+        let match_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM matches")
+            .fetch_one(ic.db.sqlite())
+            .await
+            .unwrap();
+        assert_eq!(match_count.0, 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_rating_removes_position_bias() {
+        let bias = PositionBias {
+            left: 100.0,
+            repeat: 50.0,
+            matches: 0,
+        };
+        let mut gains = Vec::new();
+        for home_side in [Some(Side::Right), None, Some(Side::Left)] {
+            let ic = ImageCollection::new_pre_configured(2).await.unwrap();
+            let m = Match {
+                home_id: 1,
+                guest_id: 2,
+                won: MatchOutcome::HomeWin,
+                home_side,
+                response_ms: None,
+                annotator: None,
+                session_id: None,
+            };
+            update_rating(
+                &ic.db,
+                &m,
+                &RatingModel {
+                    bias,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+            let (rating,): (f64,) = sqlx::query_as("SELECT rating FROM players WHERE id = 1")
+                .fetch_one(ic.db.sqlite())
+                .await
+                .unwrap();
+            gains.push(rating - 2200.0);
+        }
+        // a win on the preferred side says less about the image
+        assert!(gains[0] > gains[1]);
+        assert!(gains[1] > gains[2]);
+        assert!(gains[2] > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_order_bias_uses_previous_duel_of_annotator() {
+        let model = RatingModel {
+            bias: PositionBias {
+                left: 0.0,
+                repeat: 50.0,
+                matches: 0,
+            },
+            ..Default::default()
+        };
+        let ic = ImageCollection::new_pre_configured(6).await.unwrap();
+        for (home_id, annotator) in [(1, "alice"), (3, "bob"), (5, "alice")] {
+            let m = Match {
+                home_id,
+                guest_id: home_id + 1,
+                won: MatchOutcome::HomeWin,
+                home_side: Some(Side::Left),
+                response_ms: None,
+                annotator: Some(annotator.to_string()),
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &model).await.unwrap();
+        }
+        let ratings: Vec<(f64,)> = sqlx::query_as("SELECT rating FROM players ORDER BY id")
+            .fetch_all(ic.db.sqlite())
+            .await
+            .unwrap();
+        // the first duel of bob has no previous duel, the one of alice before it does not count
+        assert_eq!(ratings[2].0, ratings[0].0);
+        // the second duel of alice follows a win on the left
+        assert!(ratings[4].0 < ratings[0].0);
+    }
+
+    #[test]
+    fn test_response_time_weighting() {
+        let weighting: ResponseTimeWeighting = "1000,3000".parse().unwrap();
+        assert_eq!(weighting.min_confidence, 0.5);
+        assert_eq!(weighting.confidence(500), 1.0);
+        assert_eq!(weighting.confidence(2000), 0.75);
+        assert_eq!(weighting.confidence(10000), 0.5);
+        assert_eq!(weighting.score(1.0, Some(2000)), 0.875);
+        assert_eq!(weighting.score(0.0, Some(10000)), 0.25);
+        assert_eq!(weighting.score(0.0, None), 0.0);
+
+        assert!("3000,1000".parse::<ResponseTimeWeighting>().is_err());
+        assert!("1000,3000,2".parse::<ResponseTimeWeighting>().is_err());
+        assert!("1000".parse::<ResponseTimeWeighting>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_decisions_count_less() {
+        let model = RatingModel {
+            response_time: Some("1000,3000,0.2".parse().unwrap()),
+            ..Default::default()
+        };
+        let mut gains = Vec::new();
+        for response_ms in [Some(500), Some(5000)] {
+            let ic = ImageCollection::new_pre_configured(2).await.unwrap();
+            let m = Match {
+                home_id: 1,
+                guest_id: 2,
+                won: MatchOutcome::HomeWin,
+                home_side: None,
+                response_ms,
+                annotator: None,
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &model).await.unwrap();
+            let (rating,): (f64,) = sqlx::query_as("SELECT rating FROM players WHERE id = 1")
+                .fetch_one(ic.db.sqlite())
+                .await
+                .unwrap();
+            gains.push(rating - 2200.0);
+
+            let recent = ic.recent_matches(1).await.unwrap();
+            assert_eq!(recent[0].response_ms, response_ms);
+            assert_eq!(recent[0].won, MatchOutcome::HomeWin);
+        }
+        assert!(gains[1] > 0.0);
+        assert!(gains[0] > 2.0 * gains[1]);
+    }
+
+    #[tokio::test]
+    async fn test_slight_win_counts_less_than_win() {
+        let mut gains = Vec::new();
+        for won in [
+            MatchOutcome::HomeWin,
+            MatchOutcome::HomeSlightlyBetter,
+            MatchOutcome::GuestSlightlyBetter,
+        ] {
+            let ic = ImageCollection::new_pre_configured(2).await.unwrap();
+            let m = Match {
+                home_id: 1,
+                guest_id: 2,
+                won,
+                home_side: None,
+                response_ms: None,
+                annotator: None,
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+            let (rating,): (f64,) = sqlx::query_as("SELECT rating FROM players WHERE id = 1")
+                .fetch_one(ic.db.sqlite())
+                .await
+                .unwrap();
+            gains.push(rating - 2200.0);
+            assert_eq!(ic.recent_matches(1).await.unwrap()[0].won, won);
+        }
+        assert!(gains[0] > gains[1]);
+        assert!(gains[1] > 0.0);
+        assert!((gains[1] + gains[2]).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_position_bias_from_matches() {
+        let ic = ImageCollection::new_pre_configured(2).await.unwrap();
+        for _ in 0..10 {
+            let m = Match {
+                home_id: 1,
+                guest_id: 2,
+                won: MatchOutcome::HomeWin,
+                home_side: Some(Side::Left),
+                response_ms: None,
+                annotator: None,
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+        }
+        let m = Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::GuestWin,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        };
+        update_rating(&ic.db, &m, &RatingModel::default())
+            .await
+            .unwrap();
+
+        let bias = estimate_position_bias(&ic.db).await.unwrap();
+        assert_eq!(bias.matches, 10);
+        // player 1 won all of its matches on the left,
+        // which is mostly explained by its rating
+        assert!(bias.left > 0.0);
+        assert_eq!(ic.stats(0, None).await.unwrap().position_bias, bias);
+    }
+
+    #[tokio::test]
+    async fn test_draw_does_not_change_ratings_significantly() {
+        let ic = ImageCollection::new_pre_configured(2).await.unwrap();
+        let m = Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::Draw,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        };
+        update_rating(&ic.db, &m, &RatingModel::default())
+            .await
+            .unwrap();
+
+        let home: (f64, f64) = sqlx::query_as("SELECT rating, deviation FROM players WHERE id = 1")
+            .fetch_one(ic.db.sqlite())
+            .await
+            .unwrap();
+        assert!((home.0 - 2200.0).abs() < 50.0);
+        assert!(home.1 < 350.0);
+
+        let match_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM matches")
+            .fetch_one(ic.db.sqlite())
+            .await
+            .unwrap();
+        assert_eq!(match_count.0, 1);
+    }
 
     #[test]
     fn test_match_outcome_serde_roundtrip() {
@@ -668,6 +2564,24 @@ mod tests {
             serde_json::from_str::<MatchOutcome>("0.0").unwrap(),
             MatchOutcome::GuestWin
         );
+        assert_eq!(
+            serde_json::from_str::<MatchOutcome>("0.75").unwrap(),
+            MatchOutcome::HomeSlightlyBetter
+        );
+        assert_eq!(
+            serde_json::from_str::<MatchOutcome>("0.25").unwrap(),
+            MatchOutcome::GuestSlightlyBetter
+        );
+        assert_eq!(
+            serde_json::from_str::<MatchOutcome>("\"skip\"").unwrap(),
+            MatchOutcome::Skip
+        );
+        assert_eq!(
+            serde_json::from_str::<MatchOutcome>("\"flag_guest\"").unwrap(),
+            MatchOutcome::FlagGuest
+        );
+        assert!(serde_json::from_str::<MatchOutcome>("\"draw\"").is_err());
+        assert!(serde_json::from_str::<MatchOutcome>("0.7").is_err());
         assert!(serde_json::from_str::<MatchOutcome>("2.0").is_err());
         assert!(serde_json::from_str::<MatchOutcome>("-1.0").is_err());
         assert_eq!(
@@ -679,5 +2593,894 @@ mod tests {
             serde_json::to_string(&MatchOutcome::GuestWin).unwrap(),
             "0.0"
         );
+        assert_eq!(
+            serde_json::to_string(&MatchOutcome::FlagHome).unwrap(),
+            "\"flag_home\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_flag_review() {
+        let ic = ImageCollection::new_pre_configured(3).await.unwrap();
+        assert!(!ic.flag_player(42, FlagReason::Corrupt, None).await.unwrap());
+        assert!(
+            ic.flag_player(1, FlagReason::Mislabeled, Some("a cat"))
+                .await
+                .unwrap()
+        );
+        assert!(ic.flag_player(3, FlagReason::Corrupt, None).await.unwrap());
+        let open = ic.flags(Some(FlagStatus::Open)).await.unwrap();
+        assert_eq!(open.len(), 2);
+        assert_eq!(open[1].comment.as_deref(), Some("a cat"));
+        // with two excluded players, there is no opponent left
+        assert!(ic.new_duel().await.is_err());
+
+        assert!(ic.review_player(1, FlagStatus::Cleared).await.unwrap());
+        assert!(ic.review_player(3, FlagStatus::Removed).await.unwrap());
+        assert!(!ic.review_player(2, FlagStatus::Cleared).await.unwrap());
+        assert!(ic.review_player(1, FlagStatus::Open).await.is_err());
+        assert!(ic.flags(Some(FlagStatus::Open)).await.unwrap().is_empty());
+        assert_eq!(ic.flags(None).await.unwrap().len(), 2);
+        for _ in 0..10 {
+            let duel = ic.new_duel().await.unwrap();
+            assert!(duel.home_id != 3 && duel.guest_id != 3);
+        }
+    }
+
+    async fn id_of(ic: &ImageCollection, name: &str) -> u32 {
+        sqlx::query_scalar::<_, i64>("SELECT id FROM players WHERE name = ?")
+            .bind(name)
+            .fetch_one(ic.db.sqlite())
+            .await
+            .unwrap() as u32
+    }
+
+    fn gold_options(quality_control: Option<QualityControl>) -> ImageCollectionOptions {
+        ImageCollectionOptions {
+            gold_pairs: vec![
+                GoldPair {
+                    home: "0".to_string(),
+                    guest: "1".to_string(),
+                    result: MatchOutcome::GuestWin,
+                },
+                GoldPair {
+                    home: "0".to_string(),
+                    guest: "unknown.png".to_string(),
+                    result: MatchOutcome::HomeWin,
+                },
+            ],
+            gold_rate: 1_f64,
+            quality_control,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gold_pairs_are_interleaved_and_scored() {
+        let ic = ImageCollection::new_pre_configured_with(4, &gold_options(None))
+            .await
+            .unwrap();
+        let (zero, one) = (id_of(&ic, "0").await, id_of(&ic, "1").await);
+        for duel in ic.new_duels(5, None).await.unwrap() {
+            let mut ids = [duel.home_id, duel.guest_id];
+            ids.sort();
+            let mut expected = [zero, one];
+            expected.sort();
+            assert_eq!(ids, expected);
+        }
+
+        // "1" is better, once answered from each side
+        for (home_id, guest_id, won, annotator) in [
+            (one, zero, MatchOutcome::HomeWin, "alice"),
+            (zero, one, MatchOutcome::GuestSlightlyBetter, "alice"),
+            (zero, one, MatchOutcome::HomeWin, "bob"),
+        ] {
+            ic.insert_match(Match {
+                home_id,
+                guest_id,
+                won,
+                home_side: None,
+                response_ms: None,
+                annotator: Some(annotator.to_string()),
+                session_id: None,
+            })
+            .await;
+        }
+        // gold answers do not count as matches
+        assert!(ic.recent_matches(5).await.unwrap().is_empty());
+        let ratings: Vec<(f64,)> = sqlx::query_as("SELECT rating FROM players")
+            .fetch_all(ic.db.sqlite())
+            .await
+            .unwrap();
+        assert!(ratings.iter().all(|r| r.0 == 2200.0));
+
+        let annotators = ic.annotators().await.unwrap();
+        assert_eq!(annotators.len(), 2);
+        assert_eq!(annotators[0].annotator, "alice");
+        assert_eq!(annotators[0].accuracy, Some(1.0));
+        assert_eq!(annotators[1].gold_answers, 1);
+        assert_eq!(annotators[1].accuracy, Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn test_gold_pairs_are_not_regular_duels() {
+        let options = ImageCollectionOptions {
+            gold_rate: 0_f64,
+            ..gold_options(None)
+        };
+        let ic = ImageCollection::new_pre_configured_with(2, &options)
+            .await
+            .unwrap();
+        // the only pair of the collection is a gold pair
+        assert!(ic.new_duels(3, None).await.is_err());
+
+        let ic = ImageCollection::new_pre_configured_with(3, &gold_options(None))
+            .await
+            .unwrap();
+        let zero = id_of(&ic, "0").await;
+        assert!(
+            ic.flag_player(zero, FlagReason::Corrupt, None)
+                .await
+                .unwrap()
+        );
+        // the gold pair has a flagged image and is not served anymore
+        for duel in ic.new_duels(5, None).await.unwrap() {
+            assert!(ic.is_regular(&duel));
+            assert!(duel.home_id != zero && duel.guest_id != zero);
+        }
+    }
+
+    #[test]
+    fn test_down_weighted_results_count_as_partial_games() {
+        let model = RatingModel::default();
+        let rating = glicko::Rating {
+            rating: 2200_f64,
+            deviation: 350_f64,
+            time: 0,
+        };
+        let m = Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        };
+        let (home, guest) = model
+            .rate(&rating, &rating, &m, None, 0.5, 1_f64)
+            .unwrap()
+            .unwrap();
+        let (expected_home, expected_guest) = model
+            .rate(&rating, &rating, &m, None, 1_f64, 0.5)
+            .unwrap()
+            .unwrap();
+        assert_eq!(home.rating, expected_home.rating);
+        assert_eq!(home.deviation, expected_home.deviation);
+        assert_eq!(guest.rating, expected_guest.rating);
+        // the result itself is kept, a half game is less certain than a full one
+        let (full, _) = model
+            .rate(&rating, &rating, &m, None, 1_f64, 1_f64)
+            .unwrap()
+            .unwrap();
+        assert!(home.rating > rating.rating);
+        assert!(home.deviation > full.deviation);
+        assert!(
+            model
+                .rate(&rating, &rating, &m, None, 0_f64, 1_f64)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inaccurate_annotators_are_excluded() {
+        let quality_control = QualityControl {
+            min_accuracy: 0.8,
+            min_answers: 1,
+            low_accuracy: LowAccuracy::Exclude,
+        };
+        let ic = ImageCollection::new_pre_configured_with(4, &gold_options(Some(quality_control)))
+            .await
+            .unwrap();
+        let (zero, one) = (id_of(&ic, "0").await, id_of(&ic, "1").await);
+        let (two, three) = (id_of(&ic, "2").await, id_of(&ic, "3").await);
+        ic.insert_match(Match {
+            home_id: zero,
+            guest_id: one,
+            won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: Some("bob".to_string()),
+            session_id: None,
+        })
+        .await;
+        let model = RatingModel {
+            quality_control: Some(quality_control),
+            ..Default::default()
+        };
+        for annotator in ["bob", "alice"] {
+            let m = Match {
+                home_id: two,
+                guest_id: three,
+                won: MatchOutcome::HomeWin,
+                home_side: None,
+                response_ms: None,
+                annotator: Some(annotator.to_string()),
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &model).await.unwrap();
+        }
+        // only the match of alice changed the ratings
+        let (deviation,): (f64,) = sqlx::query_as("SELECT deviation FROM players WHERE id = ?")
+            .bind(two)
+            .fetch_one(ic.db.sqlite())
+            .await
+            .unwrap();
+        let recent = ic.recent_matches(5).await.unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[1].annotator.as_deref(), Some("bob"));
+        let single = ImageCollection::new_pre_configured(2).await.unwrap();
+        let m = Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        };
+        update_rating(&single.db, &m, &RatingModel::default())
+            .await
+            .unwrap();
+        let (expected,): (f64,) = sqlx::query_as("SELECT deviation FROM players WHERE id = 1")
+            .fetch_one(single.db.sqlite())
+            .await
+            .unwrap();
+        assert!((deviation - expected).abs() < 1e-9);
+        let annotators = ic.annotators().await.unwrap();
+        assert_eq!(annotators[1].annotator, "bob");
+        assert_eq!(annotators[1].weight, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_excluded_matches_are_no_games() {
+        let quality_control = QualityControl {
+            min_accuracy: 0.8,
+            min_answers: 1,
+            low_accuracy: LowAccuracy::Exclude,
+        };
+        let options = ImageCollectionOptions {
+            stopping: StoppingCriterion {
+                max_games: Some(1),
+                ..Default::default()
+            },
+            ..gold_options(Some(quality_control))
+        };
+        let ic = ImageCollection::new_pre_configured_with(4, &options)
+            .await
+            .unwrap();
+        let (zero, one) = (id_of(&ic, "0").await, id_of(&ic, "1").await);
+        let (two, three) = (id_of(&ic, "2").await, id_of(&ic, "3").await);
+        // a wrong answer on the gold pair excludes bob
+        ic.insert_match(Match {
+            home_id: zero,
+            guest_id: one,
+            won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: Some("bob".to_string()),
+            session_id: None,
+        })
+        .await;
+        let model = RatingModel {
+            quality_control: Some(quality_control),
+            ..Default::default()
+        };
+        let m = Match {
+            home_id: two,
+            guest_id: three,
+            won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: Some("bob".to_string()),
+            session_id: None,
+        };
+        update_rating(&ic.db, &m, &model).await.unwrap();
+
+        // the judgement is kept, but neither counts as a game nor towards the game budget
+        assert_eq!(ic.recent_matches(5).await.unwrap().len(), 1);
+        assert!(ic.db.players().await.unwrap().iter().all(|p| p.games == 0));
+        let status = ic.status().await.unwrap();
+        assert_eq!(status.games, 0);
+        assert!(!status.complete);
+        assert_eq!(ic.stats(0, None).await.unwrap().matches, 0);
+    }
+
+    #[tokio::test]
+    async fn test_consistency_checks_repeat_pairs_and_close_triads() {
+        let options = ImageCollectionOptions {
+            consistency_rate: 1_f64,
+            ..Default::default()
+        };
+        let ic = ImageCollection::new_pre_configured_with(5, &options)
+            .await
+            .unwrap();
+        for (home_id, guest_id, annotator) in [(1, 2, "alice"), (2, 3, "alice"), (4, 5, "bob")] {
+            let m = Match {
+                home_id,
+                guest_id,
+                won: MatchOutcome::HomeWin,
+                home_side: None,
+                response_ms: None,
+                annotator: Some(annotator.to_string()),
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+        }
+        let mut triads = 0;
+        let mut duels = Vec::new();
+        for _ in 0..20 {
+            duels.extend(ic.new_duels(3, Some("alice")).await.unwrap());
+        }
+        assert!(duels.len() >= 20);
+        for duel in duels {
+            let pair = (
+                duel.home_id.min(duel.guest_id),
+                duel.home_id.max(duel.guest_id),
+            );
+            assert!([(1, 2), (2, 3), (1, 3)].contains(&pair), "{pair:?}");
+            if pair == (1, 3) {
+                triads += 1;
+            }
+        }
+        assert!(triads > 0);
+    }
+
+    #[tokio::test]
+    async fn test_annotator_consistency_report() {
+        let ic = ImageCollection::new_pre_configured(3).await.unwrap();
+        for (home_id, guest_id, won) in [
+            (1, 2, MatchOutcome::HomeWin),
+            (2, 3, MatchOutcome::HomeWin),
+            (3, 1, MatchOutcome::HomeSlightlyBetter),
+            (2, 1, MatchOutcome::GuestWin),
+            (2, 1, MatchOutcome::HomeWin),
+        ] {
+            let m = Match {
+                home_id,
+                guest_id,
+                won,
+                home_side: None,
+                response_ms: None,
+                annotator: Some("alice".to_string()),
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+        }
+        let annotators = ic.annotators().await.unwrap();
+        assert_eq!(annotators[0].matches, 5);
+        assert_eq!(annotators[0].repeated_judgements, 2);
+        assert_eq!(annotators[0].self_consistency, Some(0.5));
+        // 1 > 2 on balance, 2 > 3 and 3 > 1
+        assert_eq!(annotators[0].intransitive_cycles, 1);
+    }
+
+    #[tokio::test]
+    async fn test_annotator_game_weight() {
+        let ic = ImageCollection::new_pre_configured(4).await.unwrap();
+        ic.set_annotator_weight("expert", 2.0).await.unwrap();
+        ic.set_annotator_weight("trainee", 1.0).await.unwrap();
+        ic.set_annotator_weight("trainee", 0.5).await.unwrap();
+        assert!(ic.set_annotator_weight("trainee", -1.0).await.is_err());
+        assert!(ic.set_annotator_weight("trainee", f64::NAN).await.is_err());
+
+        for (home_id, guest_id, annotator) in [(1, 2, "expert"), (3, 4, "trainee")] {
+            let m = Match {
+                home_id,
+                guest_id,
+                won: MatchOutcome::HomeWin,
+                home_side: None,
+                response_ms: None,
+                annotator: Some(annotator.to_string()),
+                session_id: None,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+        }
+        let player = |id: u32| {
+            sqlx::query_as::<_, (f64, f64)>("SELECT rating, deviation FROM players WHERE id = ?")
+                .bind(id)
+                .fetch_one(ic.db.sqlite())
+        };
+        let expert = player(1).await.unwrap();
+        let trainee = player(3).await.unwrap();
+        assert!(expert.0 > trainee.0);
+        assert!(expert.1 < trainee.1);
+
+        let annotators = ic.annotators().await.unwrap();
+        assert_eq!(annotators.len(), 2);
+        assert_eq!(annotators[0].annotator, "expert");
+        assert_eq!(annotators[0].game_weight, 2.0);
+        assert_eq!(annotators[1].game_weight, 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_skip_is_recorded_without_rating() {
+        let ic = ImageCollection::new_pre_configured(2).await.unwrap();
+        ic.insert_match(Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::Skip,
+            home_side: Some(Side::Left),
+            response_ms: Some(4000),
+            annotator: None,
+            session_id: None,
+        })
+        .await;
+
+        let ratings: Vec<(f64, f64)> = sqlx::query_as("SELECT rating, deviation FROM players")
+            .fetch_all(ic.db.sqlite())
+            .await
+            .unwrap();
+        assert!(ratings.iter().all(|r| *r == (2200.0, 350.0)));
+        let recent = ic.recent_matches(5).await.unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].won, MatchOutcome::Skip);
+        let stats = ic.stats(5, None).await.unwrap();
+        assert_eq!(stats.matches, 0);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(ic.status().await.unwrap().games, 0);
+    }
+
+    #[tokio::test]
+    async fn test_matches_are_grouped_into_sessions() {
+        let options = ImageCollectionOptions {
+            sessions: SessionLimits {
+                max_matches: Some(2),
+                ..SessionLimits::default()
+            },
+            ..ImageCollectionOptions::default()
+        };
+        let ic = ImageCollection::new_pre_configured_with(3, &options)
+            .await
+            .unwrap();
+        let skip = |annotator: &str| Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::Skip,
+            home_side: None,
+            response_ms: None,
+            annotator: Some(annotator.to_string()),
+            session_id: None,
+        };
+        assert_eq!(ic.session("alice").await.unwrap().session_id, None);
+        for _ in 0..2 {
+            assert!(ic.insert_match(skip("alice")).await.is_none());
+        }
+        let dropped = ic.insert_match(skip("alice")).await.unwrap();
+        assert!(dropped.on_break());
+        assert_eq!(dropped.matches, 2);
+        ic.insert_match(skip("bob")).await;
+
+        let alice = ic.session("alice").await.unwrap();
+        assert!(alice.on_break());
+        assert_eq!(alice.matches, 2);
+        assert_eq!(alice.remaining_matches, Some(0));
+        let bob = ic.session("bob").await.unwrap();
+        assert!(!bob.on_break());
+        assert_eq!(bob.remaining_matches, Some(1));
+
+        // the match during the break is dropped
+        let recent = ic.recent_matches(5).await.unwrap();
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0].session_id, bob.session_id);
+        assert!(recent[1..].iter().all(|m| m.session_id == alice.session_id));
+        assert_ne!(alice.session_id, bob.session_id);
+    }
+
+    #[tokio::test]
+    async fn test_flagged_player_leaves_matchmaking() {
+        let ic = ImageCollection::new_pre_configured(3).await.unwrap();
+        ic.insert_match(Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::FlagGuest,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        })
+        .await;
+
+        let players = ic.players(&PlayerQuery::default()).await.unwrap();
+        let flagged: Vec<u32> = players
+            .players
+            .iter()
+            .filter(|p| p.flagged)
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(flagged, vec![2]);
+        let flags = ic.flags(Some(FlagStatus::Open)).await.unwrap();
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].player_id, 2);
+        assert_eq!(flags[0].reason, FlagReason::Other);
+        assert_eq!(ic.stats(5, None).await.unwrap().flagged_players, 1);
+        assert_eq!(ic.status().await.unwrap().players, 2);
+        for _ in 0..20 {
+            let duel = ic.new_duel().await.unwrap();
+            assert!(duel.home_id != 2 && duel.guest_id != 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_matches() {
+        let ic = ImageCollection::new_pre_configured(3).await.unwrap();
+        let imported = |home: &str, guest: &str, result, timestamp: Option<&str>| ImportedMatch {
+            home: home.to_string(),
+            guest: guest.to_string(),
+            result,
+            timestamp: timestamp.map(str::to_string),
+            annotator: Some("alice".to_string()),
+        };
+        // nothing is stored if an image is unknown
+        let matches = vec![
+            imported("0", "1", MatchOutcome::HomeWin, None),
+            imported("0", "missing", MatchOutcome::Draw, None),
+        ];
+        assert!(ic.import_matches(&matches).await.is_err());
+        assert!(ic.recent_matches(10).await.unwrap().is_empty());
+
+        // nor if the database fails in the middle of the import
+        sqlx::query(
+            "CREATE TRIGGER fail_import BEFORE INSERT ON matches WHEN NEW.annotator = 'broken'
+            BEGIN SELECT RAISE(ABORT, 'broken'); END",
+        )
+        .execute(ic.db.sqlite())
+        .await
+        .unwrap();
+        let mut broken = imported("1", "2", MatchOutcome::Draw, None);
+        broken.annotator = Some("broken".to_string());
+        let matches = vec![imported("0", "1", MatchOutcome::HomeWin, None), broken];
+        assert!(ic.import_matches(&matches).await.is_err());
+        assert!(ic.recent_matches(10).await.unwrap().is_empty());
+        assert!(ic.db.players().await.unwrap().iter().all(|p| p.games == 0));
+        sqlx::query("DROP TRIGGER fail_import")
+            .execute(ic.db.sqlite())
+            .await
+            .unwrap();
+
+        let matches = vec![
+            imported("0", "1", MatchOutcome::HomeWin, Some("2024-03-01 14:05")),
+            imported("1", "2", MatchOutcome::Draw, None),
+        ];
+        assert_eq!(ic.import_matches(&matches).await.unwrap(), 2);
+
+        // same ratings as judgements of the annotators
+        let expected = ImageCollection::new_pre_configured(3).await.unwrap();
+        for (home, guest, won) in [
+            ("0", "1", MatchOutcome::HomeWin),
+            ("1", "2", MatchOutcome::Draw),
+        ] {
+            let id = |name| expected.db.player_id(name);
+            let m = Match {
+                home_id: id(home).await.unwrap().unwrap() as u32,
+                guest_id: id(guest).await.unwrap().unwrap() as u32,
+                won,
+                home_side: None,
+                response_ms: None,
+                annotator: Some("alice".to_string()),
+                session_id: None,
+            };
+            update_rating(&expected.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+        }
+        let players = ic.db.players().await.unwrap();
+        let expected = expected.db.players().await.unwrap();
+        for name in ["0", "1", "2"] {
+            let player = players.iter().find(|p| p.name == name).unwrap();
+            let expected = expected.iter().find(|p| p.name == name).unwrap();
+            assert_eq!(player.rating, expected.rating);
+            assert_eq!(player.deviation, expected.deviation);
+            assert_eq!(player.games, expected.games);
+        }
+
+        let recent = ic.recent_matches(10).await.unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[1].timestamp, "2024-03-01 14:05");
+        assert_ne!(recent[0].timestamp, "2024-03-01 14:05");
+        assert_eq!(recent[0].annotator.as_deref(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn test_import_skips_gold_pairs() {
+        let ic = ImageCollection::new_pre_configured_with(3, &gold_options(None))
+            .await
+            .unwrap();
+        let imported = |home: &str, guest: &str| ImportedMatch {
+            home: home.to_string(),
+            guest: guest.to_string(),
+            result: MatchOutcome::HomeWin,
+            timestamp: None,
+            annotator: Some("alice".to_string()),
+        };
+        // the gold pair in both orders
+        let matches = vec![imported("0", "1"), imported("1", "2"), imported("1", "0")];
+        assert_eq!(ic.import_matches(&matches).await.unwrap(), 1);
+
+        let recent = ic.recent_matches(10).await.unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(
+            (recent[0].home.as_str(), recent[0].guest.as_str()),
+            ("1", "2")
+        );
+        let players = ic.db.players().await.unwrap();
+        assert_eq!(players.iter().find(|p| p.name == "0").unwrap().games, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shared_read_access() {
+        let dir = std::env::temp_dir().join(format!("regan_shared_{}", std::process::id()));
+        let image_dir = dir.join("images");
+        std::fs::create_dir_all(&image_dir).unwrap();
+        for i in 0..3 {
+            std::fs::write(image_dir.join(format!("{i}.png")), []).unwrap();
+        }
+        let db_path = dir.join("out.db");
+        let _ = std::fs::remove_file(&db_path);
+        let options = ImageCollectionOptions {
+            db_path: format!("sqlite://{}", db_path.display()),
+            read_connections: Some(2),
+            ..ImageCollectionOptions::default()
+        };
+        let image_dir = image_dir.to_string_lossy().to_string();
+        let ic = ImageCollection::new(&options, &image_dir).await.unwrap();
+        ic.insert_match(Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::Skip,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        })
+        .await;
+        assert_eq!(ic.recent_matches(10).await.unwrap().len(), 1);
+
+        // another process can read the database while the collection is running
+        let external = sqlx::sqlite::SqlitePoolOptions::new()
+            .connect_with(
+                sqlx::sqlite::SqliteConnectOptions::new()
+                    .filename(&db_path)
+                    .read_only(true),
+            )
+            .await
+            .unwrap();
+        let matches: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM matches")
+            .fetch_one(&external)
+            .await
+            .unwrap();
+        assert_eq!(matches, 1);
+        external.close().await;
+
+        // the readers are read-only
+        assert!(
+            sqlx::query("DELETE FROM matches")
+                .execute(ic.reader.sqlite())
+                .await
+                .is_err()
+        );
+        ic.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// runs the collection on the PostgreSQL server given by `TEST_POSTGRES_URL`
+    /// in a schema of its own. Every query of the PostgreSQL backend is run at least once,
+    /// the queries are not checked at compile time.
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in TEST_POSTGRES_URL"]
+    async fn test_postgres_backend() {
+        let url = std::env::var("TEST_POSTGRES_URL").expect("TEST_POSTGRES_URL is not set");
+        let schema = format!("regan_test_{}", std::process::id());
+        let admin = sqlx::postgres::PgPoolOptions::new()
+            .connect(&url)
+            .await
+            .unwrap();
+        sqlx::raw_sql(sqlx::AssertSqlSafe(format!(
+            "DROP SCHEMA IF EXISTS {schema} CASCADE; CREATE SCHEMA {schema}"
+        )))
+        .execute(&admin)
+        .await
+        .unwrap();
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let options = ImageCollectionOptions {
+            db_path: format!("{url}{separator}options=-c%20search_path%3D{schema}"),
+            ..ImageCollectionOptions::default()
+        };
+        let image_dir = std::env::temp_dir().join(&schema);
+        std::fs::create_dir_all(&image_dir).unwrap();
+        for i in 0..5 {
+            std::fs::write(image_dir.join(format!("{i}.png")), []).unwrap();
+        }
+        let image_dir = image_dir.to_string_lossy().to_string();
+
+        let ic = ImageCollection::new(&options, &image_dir).await.unwrap();
+        let Db::Postgres(db) = &ic.db else {
+            panic!("not a postgres database");
+        };
+        assert_eq!(
+            migrations::current_version_postgres(db).await.unwrap(),
+            migrations::MIGRATIONS.len() as i64
+        );
+        let page = ic.players(&PlayerQuery::default()).await.unwrap();
+        assert_eq!(page.total, 5);
+        let id = |name: &str| page.players.iter().find(|p| p.name == name).unwrap().id;
+        assert_eq!(ic.new_duels(3, None).await.unwrap().len(), 3);
+
+        let session_id = join_session(&ic.db, &ic.sessions, "alice", unix_now())
+            .await
+            .unwrap()
+            .ok();
+        assert!(session_id.is_some());
+        let continued = join_session(&ic.db, &ic.sessions, "alice", unix_now())
+            .await
+            .unwrap()
+            .ok();
+        assert_eq!(continued, session_id);
+        for (home, guest) in [("0.png", "1.png"), ("1.png", "2.png"), ("0.png", "1.png")] {
+            let m = Match {
+                home_id: id(home),
+                guest_id: id(guest),
+                won: MatchOutcome::HomeWin,
+                home_side: Some(Side::Left),
+                response_ms: Some(900),
+                annotator: Some("alice".to_string()),
+                session_id,
+            };
+            update_rating(&ic.db, &m, &RatingModel::default())
+                .await
+                .unwrap();
+        }
+        let m = Match {
+            home_id: id("0.png"),
+            guest_id: id("1.png"),
+            won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: Some("alice".to_string()),
+            session_id,
+        };
+        record_gold_answer(&ic.db, &m, 1_f64, 1_f64).await.unwrap();
+        ic.set_annotator_weight("alice", 2_f64).await.unwrap();
+        // an excluded annotator leaves the games unchanged
+        let quality_control = QualityControl {
+            min_accuracy: 0.8,
+            min_answers: 1,
+            low_accuracy: LowAccuracy::Exclude,
+        };
+        record_gold_answer(
+            &ic.db,
+            &Match {
+                annotator: Some("carol".to_string()),
+                ..m.clone()
+            },
+            1_f64,
+            0_f64,
+        )
+        .await
+        .unwrap();
+        let model = RatingModel {
+            quality_control: Some(quality_control),
+            ..Default::default()
+        };
+        update_rating(
+            &ic.db,
+            &Match {
+                annotator: Some("carol".to_string()),
+                session_id: None,
+                ..m.clone()
+            },
+            &model,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            ic.db
+                .players()
+                .await
+                .unwrap()
+                .iter()
+                .map(|p| p.games)
+                .sum::<u64>(),
+            6
+        );
+
+        let session = ic.session("alice").await.unwrap();
+        assert_eq!(session.session_id, session_id);
+        let page = ic
+            .players(&PlayerQuery {
+                order_by: PlayerOrder::Games,
+                descending: true,
+                ..PlayerQuery::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.players[0].name, "1.png");
+        assert_eq!(page.players[0].games, 3);
+        let recent = ic.recent_matches(10).await.unwrap();
+        assert_eq!(recent.len(), 4);
+        assert_eq!(recent[1].session_id, session_id);
+        assert_eq!(ic.activity(24).await.unwrap()[0].matches, 4);
+        assert_eq!(ic.status().await.unwrap().games, 3);
+        let stats = ic.stats(1, Some(100_f64)).await.unwrap();
+        assert_eq!(stats.matches, 3);
+        assert_eq!(stats.players_below_min_games, 2);
+        assert_eq!(stats.left_win_rate, Some(1_f64));
+        let annotators = ic.annotators().await.unwrap();
+        assert_eq!(annotators[0].matches, 3);
+        assert_eq!(annotators[0].gold_correct, 1);
+        assert_eq!(annotators[0].game_weight, 2_f64);
+        assert_eq!(annotators[0].repeated_judgements, 1);
+        assert!(repeated_duel(&ic.db, "alice").await.unwrap().is_some());
+        assert!(repeated_duel(&ic.db, "bob").await.unwrap().is_none());
+        triad_duel(&ic.db, "alice").await.unwrap();
+        estimate_position_bias(&ic.db).await.unwrap();
+        assert!(!calculate_swiss_matches(&ic.db, 2).await.unwrap().is_empty());
+
+        assert!(
+            ic.flag_player(id("4.png"), FlagReason::Corrupt, Some("broken"))
+                .await
+                .unwrap()
+        );
+        let flags = ic.flags(Some(FlagStatus::Open)).await.unwrap();
+        assert_eq!(flags[0].player, "4.png");
+        assert_eq!(ic.db.flagged_players().await.unwrap(), [id("4.png") as i64]);
+        assert!(
+            ic.review_player(id("4.png"), FlagStatus::Cleared)
+                .await
+                .unwrap()
+        );
+        assert!(ic.flags(Some(FlagStatus::Open)).await.unwrap().is_empty());
+        ic.insert_match(Match {
+            won: MatchOutcome::Skip,
+            annotator: Some("bob".to_string()),
+            session_id: None,
+            ..m
+        })
+        .await;
+        let skipped = &ic.recent_matches(1).await.unwrap()[0];
+        assert_eq!(skipped.won, MatchOutcome::Skip);
+        assert!(skipped.session_id.is_some_and(|id| Some(id) != session_id));
+        ic.close().await;
+
+        // reopening keeps the data and removes missing images
+        std::fs::remove_file(format!("{image_dir}/4.png")).unwrap();
+        let ic = ImageCollection::new(&options, &image_dir).await.unwrap();
+        assert_eq!(ic.players(&PlayerQuery::default()).await.unwrap().total, 4);
+        assert_eq!(ic.status().await.unwrap().games, 3);
+        let imported = ImportedMatch {
+            home: "2.png".to_string(),
+            guest: "3.png".to_string(),
+            result: MatchOutcome::GuestWin,
+            timestamp: Some("2024-03-01 14:05".to_string()),
+            annotator: None,
+        };
+        assert_eq!(ic.import_matches(&[imported]).await.unwrap(), 1);
+        let recent = ic.recent_matches(1).await.unwrap();
+        assert_eq!(recent[0].timestamp, "2024-03-01 14:05");
+        assert_eq!(recent[0].won, MatchOutcome::GuestWin);
+        ic.close().await;
+
+        sqlx::raw_sql(sqlx::AssertSqlSafe(format!("DROP SCHEMA {schema} CASCADE")))
+            .execute(&admin)
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&image_dir).unwrap();
     }
 }
@@ -0,0 +1,249 @@
+//! Storage without a database for simulations.
+//!
+//! It keeps the players and the matches in memory and shares the rating update and the
+//! matchmaking with the SQL databases. Gold pairs and annotator weights are not stored,
+//! so every annotator has a weight of 1.
+
+use crate::storage::{Player, Storage, shuffle_sides, swiss_duels};
+use crate::{Duel, Match, Matchmaking, PlayerInfo, RatingModel, glicko, winner_side};
+use anyhow::{Result, anyhow};
+use rand::prelude::*;
+use std::sync::Mutex;
+use tracing::*;
+
+pub struct MemoryStorage {
+    state: Mutex<State>,
+}
+
+struct State {
+    /// the player with the id i is at the index i - 1
+    players: Vec<PlayerInfo>,
+    matches: Vec<Match>,
+}
+
+impl State {
+    fn player(&self, id: u32) -> Result<&PlayerInfo> {
+        (id as usize)
+            .checked_sub(1)
+            .and_then(|i| self.players.get(i))
+            .ok_or_else(|| anyhow!("player {} does not exist", id))
+    }
+
+    fn player_mut(&mut self, id: u32) -> Result<&mut PlayerInfo> {
+        (id as usize)
+            .checked_sub(1)
+            .and_then(|i| self.players.get_mut(i))
+            .ok_or_else(|| anyhow!("player {} does not exist", id))
+    }
+
+    fn active_players(&self) -> impl Iterator<Item = &PlayerInfo> {
+        self.players.iter().filter(|p| !p.flagged)
+    }
+
+    /// the players with the highest deviation and a random opponent inside of their
+    /// confidence interval, or any random opponent if there is none
+    fn random_matches(&self, n_matches: usize) -> Vec<Duel> {
+        let mut home_players: Vec<&PlayerInfo> = self.active_players().collect();
+        home_players.sort_by(|a, b| b.deviation.total_cmp(&a.deviation));
+        let mut rng = rand::rng();
+        let mut result = Vec::new();
+        for home in home_players.into_iter().take(n_matches) {
+            let upper = home.rating + 0.96 * home.deviation;
+            let lower = home.rating - 0.96 * home.deviation;
+            let opponents: Vec<&PlayerInfo> =
+                self.active_players().filter(|p| p.id != home.id).collect();
+            let close: Vec<&PlayerInfo> = opponents
+                .iter()
+                .copied()
+                .filter(|p| p.rating <= upper && p.rating >= lower)
+                .collect();
+            let guest = match close.choose(&mut rng) {
+                Some(guest) => Some(guest),
+                None => opponents.choose(&mut rng),
+            };
+            if let Some(guest) = guest {
+                info!("Selected: {} - {}", home.name, guest.name);
+                result.push(Duel {
+                    home: home.name.clone(),
+                    home_id: home.id,
+                    guest: guest.name.clone(),
+                    guest_id: guest.id,
+                });
+            }
+        }
+        result
+    }
+
+    fn swiss_matches(&self, n_matches: usize) -> Vec<Duel> {
+        let players: Vec<Player> = self
+            .active_players()
+            .map(|p| Player {
+                id: p.id as i64,
+                rating: p.rating,
+                deviation: p.deviation,
+                name: p.name.clone(),
+            })
+            .collect();
+        let matches: Vec<(i64, i64)> = self
+            .matches
+            .iter()
+            .filter(|m| m.won.score().is_some())
+            .map(|m| (m.home_id as i64, m.guest_id as i64))
+            .collect();
+        swiss_duels(&players, &matches, n_matches)
+    }
+}
+
+impl MemoryStorage {
+    /// creates a storage with the players 0..num in random order,
+    /// like [`ImageCollection::new_pre_configured`](crate::ImageCollection::new_pre_configured)
+    pub fn new_pre_configured(num: u32) -> MemoryStorage {
+        let mut numbers: Vec<u32> = (0..num).collect();
+        numbers.shuffle(&mut rand::rng());
+        let players = numbers
+            .into_iter()
+            .enumerate()
+            .map(|(i, n)| PlayerInfo {
+                id: i as u32 + 1,
+                name: n.to_string(),
+                rating: 2200_f64,
+                deviation: 350_f64,
+                games: 0,
+                flagged: false,
+            })
+            .collect();
+        MemoryStorage {
+            state: Mutex::new(State {
+                players,
+                matches: Vec::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Storage for MemoryStorage {
+    async fn players(&self) -> Result<Vec<PlayerInfo>> {
+        Ok(self.state().players.clone())
+    }
+
+    async fn update_rating(&self, m: &Match, model: &RatingModel) -> Result<()> {
+        let mut state = self.state();
+        // the previous duel of the same annotator, like in the database
+        let previous_winner = m.annotator.as_ref().and_then(|annotator| {
+            let p = state
+                .matches
+                .iter()
+                .rev()
+                .find(|p| p.annotator.as_ref() == Some(annotator))?;
+            winner_side(p.home_side, p.won.score()?)
+        });
+        let rating = |p: &PlayerInfo| glicko::Rating {
+            rating: p.rating,
+            deviation: p.deviation,
+            time: 0,
+        };
+        let home = rating(state.player(m.home_id)?);
+        let guest = rating(state.player(m.guest_id)?);
+        if let Some((home, guest)) = model.rate(&home, &guest, m, previous_winner, 1_f64, 1_f64)? {
+            for (id, new) in [(m.home_id, home), (m.guest_id, guest)] {
+                let player = state.player_mut(id)?;
+                player.rating = new.rating;
+                player.deviation = new.deviation;
+                player.games += 1;
+            }
+        }
+        state.matches.push(m.clone());
+        Ok(())
+    }
+
+    async fn insert_match(&self, m: &Match) -> Result<()> {
+        let mut state = self.state();
+        if let Some(id) = m.flagged_player() {
+            state.player_mut(id)?.flagged = true;
+        }
+        state.matches.push(m.clone());
+        Ok(())
+    }
+
+    async fn candidates(&self, count: usize, matchmaking: Matchmaking) -> Result<Vec<Duel>> {
+        let state = self.state();
+        let mut duels = match matchmaking {
+            Matchmaking::Random => state.random_matches(count),
+            Matchmaking::Swiss => state.swiss_matches(count),
+        };
+        shuffle_sides(&mut duels);
+        Ok(duels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ImageCollection, MatchOutcome, Side};
+
+    fn judged(home_id: u32, guest_id: u32, won: MatchOutcome) -> Match {
+        Match {
+            home_id,
+            guest_id,
+            won,
+            home_side: Some(Side::Left),
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ratings_match_sqlite() {
+        let memory = MemoryStorage::new_pre_configured(3);
+        let sqlite = ImageCollection::new_pre_configured(3).await.unwrap();
+        let model = RatingModel::default();
+        for m in [
+            judged(1, 2, MatchOutcome::HomeWin),
+            judged(2, 3, MatchOutcome::HomeSlightlyBetter),
+            judged(3, 1, MatchOutcome::Draw),
+        ] {
+            memory.update_rating(&m, &model).await.unwrap();
+            sqlite.db.update_rating(&m, &model).await.unwrap();
+        }
+        let expected = sqlite.db.players().await.unwrap();
+        let players = memory.players().await.unwrap();
+        assert_eq!(players.len(), expected.len());
+        for (p, e) in players.iter().zip(&expected) {
+            assert_eq!(p.id, e.id);
+            assert!((p.rating - e.rating).abs() < 1e-9);
+            assert!((p.deviation - e.deviation).abs() < 1e-9);
+            assert_eq!(p.games, e.games);
+        }
+        assert!(
+            memory
+                .update_rating(&judged(1, 2, MatchOutcome::Skip), &model)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_candidates_skip_flagged_players() {
+        let memory = MemoryStorage::new_pre_configured(4);
+        memory
+            .insert_match(&judged(1, 2, MatchOutcome::FlagHome))
+            .await
+            .unwrap();
+        for matchmaking in [Matchmaking::Random, Matchmaking::Swiss] {
+            let duels = memory.candidates(3, matchmaking).await.unwrap();
+            assert!(!duels.is_empty());
+            for duel in duels {
+                assert_ne!(duel.home_id, duel.guest_id);
+                assert!(duel.home_id != 1 && duel.guest_id != 1);
+            }
+        }
+        let players = memory.players().await.unwrap();
+        assert!(players[0].flagged);
+        assert!(players.iter().all(|p| p.games == 0));
+    }
+}
@@ -0,0 +1,324 @@
+//! Versioned changes of the database schema.
+//!
+//! Every migration runs once, in the order of its version, and gets recorded in the
+//! `schema_version` table. A change of the schema is added as a new file in `migrations/`,
+//! with its PostgreSQL dialect in `migrations/postgres/`, and appended to [`MIGRATIONS`].
+//! Released migrations must not be edited.
+//! The database for the compile time checks of the queries is created by applying all files,
+//! e.g. `cat image_collection/migrations/*.sql | sqlite3 test.db`.
+
+use anyhow::{Result, bail};
+use sqlx::{PgPool, SqlitePool};
+use tracing::*;
+
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+    /// the same change for PostgreSQL
+    pub postgres: &'static str,
+}
+
+/// the schema at the time the versioning was introduced.
+/// Unversioned databases already have it and get the later migrations applied.
+const BASELINE: i64 = 1;
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: BASELINE,
+        description: "baseline",
+        sql: include_str!("../migrations/0001_baseline.sql"),
+        postgres: include_str!("../migrations/postgres/0001_baseline.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "displayed side of the home player",
+        sql: include_str!("../migrations/0002_home_side.sql"),
+        postgres: include_str!("../migrations/postgres/0002_home_side.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "response times",
+        sql: include_str!("../migrations/0003_response_time.sql"),
+        postgres: include_str!("../migrations/postgres/0003_response_time.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "graded results and unrated outcomes",
+        sql: include_str!("../migrations/0004_unrated_outcomes.sql"),
+        postgres: include_str!("../migrations/postgres/0004_unrated_outcomes.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "image flags",
+        sql: include_str!("../migrations/0005_flags.sql"),
+        postgres: include_str!("../migrations/postgres/0005_flags.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "annotators and gold answers",
+        sql: include_str!("../migrations/0006_gold_answers.sql"),
+        postgres: include_str!("../migrations/postgres/0006_gold_answers.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "annotator sessions",
+        sql: include_str!("../migrations/0007_sessions.sql"),
+        postgres: include_str!("../migrations/postgres/0007_sessions.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "annotator weights",
+        sql: include_str!("../migrations/0008_annotator_weights.sql"),
+        postgres: include_str!("../migrations/postgres/0008_annotator_weights.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "excluded matches",
+        sql: include_str!("../migrations/0009_excluded_matches.sql"),
+        postgres: include_str!("../migrations/postgres/0009_excluded_matches.sql"),
+    },
+];
+
+/// brings the database to the latest version of the schema
+pub async fn migrate(db: &SqlitePool) -> Result<()> {
+    run(db, MIGRATIONS).await
+}
+
+/// brings the PostgreSQL database to the latest version of the schema
+pub async fn migrate_postgres(db: &PgPool) -> Result<()> {
+    run_postgres(db, MIGRATIONS).await
+}
+
+/// version of the schema of the database, 0 for an empty or unversioned database
+pub async fn current_version(db: &SqlitePool) -> Result<i64> {
+    let (exists,): (bool,) = sqlx::query_as(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+    )
+    .fetch_one(db)
+    .await?;
+    if !exists {
+        return Ok(0);
+    }
+    let (version,): (i64,) = sqlx::query_as("SELECT COALESCE(MAX(version), 0) FROM schema_version")
+        .fetch_one(db)
+        .await?;
+    Ok(version)
+}
+
+async fn run(db: &SqlitePool, migrations: &[Migration]) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_version(
+            version INTEGER PRIMARY KEY NOT NULL,
+            description TEXT NOT NULL,
+            applied TEXT NOT NULL
+        )",
+    )
+    .execute(db)
+    .await?;
+    let current = current_version(db).await?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if current > latest {
+        bail!(
+            "the database has the schema version {current}, but this release only supports up to version {latest}"
+        );
+    }
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        info!(
+            "Migrate the database to version {}: {}.",
+            migration.version, migration.description
+        );
+        let mut tx = db.begin().await?;
+        sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+        sqlx::query(
+            "INSERT INTO schema_version (version, description, applied)
+            VALUES (?, ?, strftime('%Y-%m-%d %H:%M','now'))",
+        )
+        .bind(migration.version)
+        .bind(migration.description)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// version of the schema of the PostgreSQL database, 0 for an empty database
+pub async fn current_version_postgres(db: &PgPool) -> Result<i64> {
+    let (exists,): (bool,) = sqlx::query_as("SELECT to_regclass('schema_version') IS NOT NULL")
+        .fetch_one(db)
+        .await?;
+    if !exists {
+        return Ok(0);
+    }
+    let (version,): (i64,) = sqlx::query_as("SELECT COALESCE(MAX(version), 0) FROM schema_version")
+        .fetch_one(db)
+        .await?;
+    Ok(version)
+}
+
+/// like `run`, for PostgreSQL
+async fn run_postgres(db: &PgPool, migrations: &[Migration]) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_version(
+            version BIGINT PRIMARY KEY NOT NULL,
+            description TEXT NOT NULL,
+            applied TEXT NOT NULL
+        )",
+    )
+    .execute(db)
+    .await?;
+    let current = current_version_postgres(db).await?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if current > latest {
+        bail!(
+            "the database has the schema version {current}, but this release only supports up to version {latest}"
+        );
+    }
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        info!(
+            "Migrate the database to version {}: {}.",
+            migration.version, migration.description
+        );
+        let mut tx = db.begin().await?;
+        sqlx::raw_sql(migration.postgres).execute(&mut *tx).await?;
+        sqlx::query(
+            "INSERT INTO schema_version (version, description, applied)
+            VALUES ($1, $2, to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI'))",
+        )
+        .bind(migration.version)
+        .bind(migration.description)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn memory_db() -> SqlitePool {
+        sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_migrate_new_database() {
+        let db = memory_db().await;
+        migrate(&db).await.unwrap();
+        assert_eq!(current_version(&db).await.unwrap(), MIGRATIONS.len() as i64);
+        // running it twice is fine
+        migrate(&db).await.unwrap();
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM schema_version")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(count, MIGRATIONS.len() as i64);
+        // the matches of an annotator are looked up for the consistency checks and the order bias
+        let (indexed,): (bool,) = sqlx::query_as(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'index' AND name = 'idx_matches_annotator'",
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert!(indexed);
+    }
+
+    #[tokio::test]
+    async fn test_later_migrations_run_once_in_order() {
+        let db = memory_db().await;
+        let migrations = [
+            Migration {
+                version: 1,
+                description: "baseline",
+                sql: "CREATE TABLE players(id INTEGER PRIMARY KEY NOT NULL, name TEXT NOT NULL, rating REAL NOT NULL, deviation REAL NOT NULL);
+                    CREATE TABLE matches(id INTEGER PRIMARY KEY NOT NULL, result REAL);",
+                postgres: "",
+            },
+            Migration {
+                version: 2,
+                description: "add a note",
+                sql: "ALTER TABLE players ADD COLUMN note TEXT",
+                postgres: "",
+            },
+        ];
+        run(&db, &migrations[..1]).await.unwrap();
+        assert_eq!(current_version(&db).await.unwrap(), 1);
+        run(&db, &migrations).await.unwrap();
+        run(&db, &migrations).await.unwrap();
+        assert_eq!(current_version(&db).await.unwrap(), 2);
+        sqlx::query(
+            "INSERT INTO players (name, rating, deviation, note) VALUES ('a.png', 2200, 350, 'x')",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        // a newer database is not touched by an older release
+        assert!(run(&db, &migrations[..1]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_schema_adds_columns() {
+        let db = memory_db().await;
+        // schema of version 0.3
+        sqlx::query(
+            "CREATE TABLE players(id INTEGER PRIMARY KEY NOT NULL, name TEXT UNIQUE NOT NULL,
+                rating REAL NOT NULL, deviation REAL NOT NULL)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TABLE matches(id INTEGER PRIMARY KEY NOT NULL, home_players_id INTEGER NOT NULL,
+                guest_players_id INTEGER NOT NULL, result REAL NOT NULL CHECK (result IN (0.0, 0.5, 1.0)),
+                timestamp TEXT NOT NULL)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO players VALUES (1, 'a.png', 2200, 350), (2, 'b.png', 2200, 350)")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO matches VALUES (1, 1, 2, 1.0, '2024-01-01 00:00')")
+            .execute(&db)
+            .await
+            .unwrap();
+        migrate(&db).await.unwrap();
+        // running it twice is fine
+        migrate(&db).await.unwrap();
+        assert_eq!(current_version(&db).await.unwrap(), MIGRATIONS.len() as i64);
+        sqlx::query(
+            "INSERT INTO matches VALUES (2, 1, 2, 0.75, '2024-01-01 00:00', 'left', 1200, NULL, 'alice', NULL, FALSE)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO matches (home_players_id, guest_players_id, timestamp, outcome) VALUES (1, 2, '2024-01-01 00:00', 'skip')")
+            .execute(&db)
+            .await
+            .unwrap();
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM matches")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+        let (flagged,): (bool,) = sqlx::query_as("SELECT flagged FROM players WHERE id = 1")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert!(!flagged);
+        // tables added after version 0.3 exist
+        let (sessions,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM sessions")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(sessions, 0);
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde::de::DeserializeOwned;
+
+use crate::MatchOutcome;
+
+/// A row of a csv file with a judged pair of images, like a gold pair or an imported match
+pub(crate) trait PairRow: DeserializeOwned {
+    /// result in terms of the home image
+    fn result(&self) -> MatchOutcome;
+
+    /// checks and normalizes the other columns of the row
+    fn validate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// reads the rows of a csv file with a header. Every row needs a score as result.
+/// `kind` names the rows in the error messages.
+pub(crate) fn read<T: PairRow>(path: &Path, kind: &str) -> Result<Vec<T>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("can not read {}s from {}", kind, path.display()))?;
+    let mut rows = Vec::new();
+    for (line, row) in reader.deserialize::<T>().enumerate() {
+        // the header is the first line
+        let line = line + 2;
+        let mut row = row.with_context(|| format!("invalid {} in line {}", kind, line))?;
+        if row.result().score().is_none() {
+            bail!("{} in line {} needs a score as result", kind, line);
+        }
+        row.validate()
+            .with_context(|| format!("invalid {} in line {}", kind, line))?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// csv file in the temporary directory, which is deleted at the end of the test
+#[cfg(test)]
+pub(crate) struct TempCsv(std::path::PathBuf);
+
+#[cfg(test)]
+impl TempCsv {
+    pub(crate) fn new(name: &str) -> TempCsv {
+        TempCsv(std::env::temp_dir().join(format!("{}_{}.csv", name, std::process::id())))
+    }
+
+    /// replaces the content of the file
+    pub(crate) fn write(&self, content: &str) -> &Path {
+        std::fs::write(&self.0, content).unwrap();
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempCsv {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+
+/// Limits of the annotation sessions of an annotator
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionLimits {
+    /// judgements per session
+    pub max_matches: Option<u64>,
+    /// length of a session in seconds
+    pub max_seconds: Option<u64>,
+    /// a pause of this many seconds ends a session.
+    /// After reaching a limit, the annotator has to pause this long.
+    pub break_seconds: u64,
+}
+
+impl Default for SessionLimits {
+    fn default() -> Self {
+        SessionLimits {
+            max_matches: None,
+            max_seconds: None,
+            break_seconds: 15 * 60,
+        }
+    }
+}
+
+/// The latest session of an annotator, times in unix seconds
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id: i64,
+    pub started: i64,
+    pub last_active: i64,
+    pub matches: u64,
+}
+
+/// What happens with the next judgement of an annotator
+#[derive(Debug, PartialEq)]
+pub enum Next {
+    /// the judgement belongs to the session with this id
+    Continue(i64),
+    /// the judgement starts a new session
+    Start,
+    /// the annotator has to pause until the given unix time
+    Break { until: i64 },
+}
+
+impl SessionLimits {
+    /// true, if the session reached one of the limits
+    pub fn is_exhausted(&self, session: &Session, now: i64) -> bool {
+        self.max_matches.is_some_and(|max| session.matches >= max)
+            || self
+                .max_seconds
+                .is_some_and(|max| now - session.started >= max as i64)
+    }
+
+    pub fn next(&self, latest: Option<&Session>, now: i64) -> Next {
+        let Some(session) = latest else {
+            return Next::Start;
+        };
+        let until = session.last_active + self.break_seconds as i64;
+        if now >= until {
+            Next::Start
+        } else if self.is_exhausted(session, now) {
+            Next::Break { until }
+        } else {
+            Next::Continue(session.id)
+        }
+    }
+}
+
+/// Session of an annotator as reported to the client
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SessionStatus {
+    /// id of the running session, None if the next judgement starts a new session
+    pub session_id: Option<i64>,
+    /// judgements in the running session
+    pub matches: u64,
+    /// judgements left in the running session, None without a limit
+    pub remaining_matches: Option<u64>,
+    /// seconds until the annotator can continue, 0 if no break is needed
+    pub break_seconds: u64,
+}
+
+impl SessionStatus {
+    pub fn new(limits: &SessionLimits, latest: Option<&Session>, now: i64) -> SessionStatus {
+        match (limits.next(latest, now), latest) {
+            (Next::Continue(id), Some(session)) => SessionStatus {
+                session_id: Some(id),
+                matches: session.matches,
+                remaining_matches: limits
+                    .max_matches
+                    .map(|max| max.saturating_sub(session.matches)),
+                break_seconds: 0,
+            },
+            (Next::Break { until }, Some(session)) => SessionStatus {
+                session_id: Some(session.id),
+                matches: session.matches,
+                remaining_matches: limits.max_matches.map(|_| 0),
+                break_seconds: (until - now) as u64,
+            },
+            _ => SessionStatus {
+                session_id: None,
+                matches: 0,
+                remaining_matches: limits.max_matches,
+                break_seconds: 0,
+            },
+        }
+    }
+
+    pub fn on_break(&self) -> bool {
+        self.break_seconds > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(matches: u64) -> Session {
+        Session {
+            id: 7,
+            started: 1000,
+            last_active: 1500,
+            matches,
+        }
+    }
+
+    #[test]
+    fn test_pause_starts_new_session() {
+        let limits = SessionLimits::default();
+        assert_eq!(limits.next(None, 1000), Next::Start);
+        assert_eq!(limits.next(Some(&session(3)), 1600), Next::Continue(7));
+        assert_eq!(limits.next(Some(&session(3)), 1500 + 15 * 60), Next::Start);
+    }
+
+    #[test]
+    fn test_limits_enforce_break() {
+        let limits = SessionLimits {
+            max_matches: Some(10),
+            max_seconds: Some(3600),
+            break_seconds: 600,
+        };
+        assert_eq!(limits.next(Some(&session(9)), 1600), Next::Continue(7));
+        assert_eq!(
+            limits.next(Some(&session(10)), 1600),
+            Next::Break { until: 2100 }
+        );
+        assert_eq!(limits.next(Some(&session(10)), 2100), Next::Start);
+
+        // too long, even though the last judgement was recent
+        let long = Session {
+            last_active: 4500,
+            ..session(5)
+        };
+        assert_eq!(limits.next(Some(&long), 4600), Next::Break { until: 5100 });
+
+        let status = SessionStatus::new(&limits, Some(&session(10)), 1600);
+        assert!(status.on_break());
+        assert_eq!(status.break_seconds, 500);
+        assert_eq!(status.remaining_matches, Some(0));
+        let status = SessionStatus::new(&limits, Some(&session(4)), 1600);
+        assert!(!status.on_break());
+        assert_eq!(status.remaining_matches, Some(6));
+        let status = SessionStatus::new(&limits, Some(&session(10)), 2100);
+        assert_eq!(status.session_id, None);
+        assert_eq!(status.remaining_matches, Some(10));
+    }
+}
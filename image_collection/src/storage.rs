@@ -0,0 +1,676 @@
+//! Storage of the players and matches.
+//!
+//! The rating updates, the recording of matches and the matchmaking go through the [`Storage`]
+//! trait. The SQL databases implement it with SQLite or PostgreSQL, [`MemoryStorage`] keeps
+//! everything in memory for fast simulations.
+//!
+//! The queries of each database live in a module of their own. [`Db`] and [`Tx`] forward to
+//! the module of the backend, so the rest of the crate does not depend on the SQL dialect.
+//!
+//! [`MemoryStorage`]: crate::MemoryStorage
+
+mod postgres;
+mod sqlite;
+
+use crate::{
+    Duel, FlagReason, Match, Matchmaking, PlayerInfo, QualityControl, RatingModel, glicko, swiss,
+    winner_side,
+};
+use anyhow::Result;
+use rand::prelude::*;
+use sqlx::{PgPool, SqlitePool};
+use tokio_stream::StreamExt;
+use tracing::*;
+
+/// Storage of the players and matches of a campaign
+pub trait Storage {
+    /// all players with their ratings, ordered by id
+    fn players(&self) -> impl Future<Output = Result<Vec<PlayerInfo>>> + Send;
+
+    /// updates the ratings of both players by a rated match and stores the match
+    fn update_rating(
+        &self,
+        m: &Match,
+        model: &RatingModel,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// stores a match which does not change the ratings, like a skipped pair.
+    /// A flagged player is excluded from the matchmaking until it gets reviewed.
+    fn insert_match(&self, m: &Match) -> impl Future<Output = Result<()>> + Send;
+
+    /// computes the next `count` duels of the matchmaking
+    fn candidates(
+        &self,
+        count: usize,
+        matchmaking: Matchmaking,
+    ) -> impl Future<Output = Result<Vec<Duel>>> + Send;
+}
+
+/// runs the query of the same name in the module of the backend
+macro_rules! backend {
+    (db $db:expr, $query:ident($($arg:expr),*)) => {
+        match $db {
+            Db::Sqlite(db) => sqlite::$query(db $(, $arg)*).await,
+            Db::Postgres(db) => postgres::$query(db $(, $arg)*).await,
+        }
+    };
+    (tx $tx:expr, $query:ident($($arg:expr),*)) => {
+        match $tx {
+            Tx::Sqlite(tx) => sqlite::$query(&mut **tx $(, $arg)*).await,
+            Tx::Postgres(tx) => postgres::$query(&mut **tx $(, $arg)*).await,
+        }
+    };
+}
+
+/// Storage backend, selected by the scheme of the database url.
+/// The queries of SQLite are checked at compile time, the ones of PostgreSQL mirror them.
+#[derive(Clone)]
+pub(crate) enum Db {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
+}
+
+/// A player with the number of its rated games
+#[derive(sqlx::FromRow)]
+pub(crate) struct PlayerRow {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) rating: f64,
+    pub(crate) deviation: f64,
+    pub(crate) flagged: bool,
+    pub(crate) games: i64,
+}
+
+impl From<PlayerRow> for PlayerInfo {
+    fn from(p: PlayerRow) -> Self {
+        PlayerInfo {
+            id: p.id as u32,
+            name: p.name,
+            rating: p.rating,
+            deviation: p.deviation,
+            games: p.games as u64,
+            flagged: p.flagged,
+        }
+    }
+}
+
+/// A match with the names of its players
+#[derive(sqlx::FromRow)]
+pub(crate) struct MatchRow {
+    pub(crate) id: i64,
+    pub(crate) home_players_id: i64,
+    pub(crate) home: String,
+    pub(crate) guest_players_id: i64,
+    pub(crate) guest: String,
+    pub(crate) result: Option<f64>,
+    pub(crate) outcome: Option<String>,
+    pub(crate) timestamp: String,
+    pub(crate) home_side: Option<String>,
+    pub(crate) response_ms: Option<i64>,
+    pub(crate) annotator: Option<String>,
+    pub(crate) session_id: Option<i64>,
+}
+
+/// A flag with the name of the flagged player
+#[derive(sqlx::FromRow)]
+pub(crate) struct FlagRow {
+    pub(crate) id: i64,
+    pub(crate) players_id: i64,
+    pub(crate) name: String,
+    pub(crate) reason: String,
+    pub(crate) comment: Option<String>,
+    pub(crate) timestamp: String,
+    pub(crate) status: String,
+}
+
+/// counts over all matches
+#[derive(sqlx::FromRow)]
+pub(crate) struct MatchSummary {
+    pub(crate) matches: i64,
+    pub(crate) skipped: i64,
+    pub(crate) draws: i64,
+    /// time between the first and the last match
+    pub(crate) hours: f64,
+    pub(crate) last_hour: i64,
+    /// rated matches with a known side and a winner
+    pub(crate) sided: i64,
+    pub(crate) left_wins: i64,
+}
+
+/// A match with the current rating difference of its players
+#[derive(sqlx::FromRow)]
+pub(crate) struct BiasRow {
+    pub(crate) home_side: Option<String>,
+    pub(crate) result: Option<f64>,
+    pub(crate) annotator: Option<String>,
+    pub(crate) rating_diff: f64,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct DuelRow {
+    pub(crate) home_id: i64,
+    pub(crate) home: String,
+    pub(crate) guest_id: i64,
+    pub(crate) guest: String,
+}
+
+impl From<DuelRow> for Duel {
+    fn from(d: DuelRow) -> Self {
+        Duel {
+            home: d.home,
+            home_id: d.home_id as u32,
+            guest: d.guest,
+            guest_id: d.guest_id as u32,
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct Rating {
+    pub(crate) id: i64,
+    pub(crate) rating: f64,
+    pub(crate) deviation: f64,
+}
+
+impl Db {
+    /// opens the database of the url and migrates it.
+    /// Returns the writer and the reader, which use the `read_connections` on SQLite.
+    pub(crate) async fn connect(url: &str, read_connections: Option<u32>) -> Result<(Db, Db)> {
+        if is_postgres_url(url) {
+            let db = Db::Postgres(postgres::connect(url).await?);
+            Ok((db.clone(), db))
+        } else {
+            let (db, reader) = sqlite::connect(url, read_connections).await?;
+            Ok((Db::Sqlite(db), Db::Sqlite(reader)))
+        }
+    }
+
+    /// an empty SQLite database in memory
+    pub(crate) async fn in_memory() -> Result<Db> {
+        Ok(Db::Sqlite(sqlite::in_memory().await?))
+    }
+
+    pub(crate) async fn close(&self) {
+        match self {
+            Db::Sqlite(db) => db.close().await,
+            Db::Postgres(db) => db.close().await,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn sqlite(&self) -> &SqlitePool {
+        match self {
+            Db::Sqlite(db) => db,
+            Db::Postgres(_) => panic!("not a sqlite database"),
+        }
+    }
+
+    /// a page of the players, ordered by the column `order_by` and then by id
+    pub(crate) async fn player_page(
+        &self,
+        order_by: &str,
+        descending: bool,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<PlayerRow>> {
+        let direction = if descending { -1 } else { 1 };
+        backend!(db self, player_page(order_by, direction, limit, offset))
+    }
+
+    pub(crate) async fn count_players(&self) -> Result<u64> {
+        Ok(backend!(db self, count_players())? as u64)
+    }
+
+    pub(crate) async fn player_names(&self) -> Result<Vec<String>> {
+        backend!(db self, player_names())
+    }
+
+    /// id of the player with the given name
+    pub(crate) async fn player_id(&self, name: &str) -> Result<Option<i64>> {
+        backend!(db self, player_id(name))
+    }
+
+    /// ids of the players flagged for review
+    pub(crate) async fn flagged_players(&self) -> Result<Vec<i64>> {
+        backend!(db self, flagged_players())
+    }
+
+    pub(crate) async fn count_flagged(&self) -> Result<u64> {
+        Ok(backend!(db self, count_flagged())? as u64)
+    }
+
+    /// the players taking part in the matchmaking
+    pub(crate) async fn unflagged_players(&self) -> Result<Vec<Player>> {
+        backend!(db self, unflagged_players())
+    }
+
+    /// rating and deviation of the players taking part in the matchmaking
+    pub(crate) async fn unflagged_ratings(&self) -> Result<Vec<(f64, f64)>> {
+        backend!(db self, unflagged_ratings())
+    }
+
+    /// ids and names of the given players, if they are not flagged
+    pub(crate) async fn unflagged_names(&self, a: i64, b: i64) -> Result<Vec<(i64, String)>> {
+        backend!(db self, unflagged_names(a, b))
+    }
+
+    /// returns the last `limit` played matches, newest first
+    pub(crate) async fn recent_matches(&self, limit: u32) -> Result<Vec<MatchRow>> {
+        backend!(db self, recent_matches(limit))
+    }
+
+    /// the number of matches per hour of the last `hours` hours
+    pub(crate) async fn activity(&self, hours: u32) -> Result<Vec<(String, i64)>> {
+        backend!(db self, activity(hours))
+    }
+
+    pub(crate) async fn count_rated_matches(&self) -> Result<u64> {
+        Ok(backend!(db self, count_rated_matches())? as u64)
+    }
+
+    pub(crate) async fn match_summary(&self) -> Result<MatchSummary> {
+        backend!(db self, match_summary())
+    }
+
+    /// all matches in the order they were played
+    pub(crate) async fn bias_rows(&self) -> Result<Vec<BiasRow>> {
+        backend!(db self, bias_rows())
+    }
+
+    /// a random rated pair of the annotator without flagged players
+    pub(crate) async fn repeated_pair(&self, annotator: &str) -> Result<Option<Duel>> {
+        Ok(backend!(db self, repeated_pair(annotator))?.map(Duel::from))
+    }
+
+    /// player ids of a random rated match of the annotator
+    pub(crate) async fn random_pair(&self, annotator: &str) -> Result<Option<(i64, i64)>> {
+        backend!(db self, random_pair(annotator))
+    }
+
+    /// player ids of the rated matches of the annotator with the given player
+    pub(crate) async fn pairs_of(&self, annotator: &str, id: i64) -> Result<Vec<(i64, i64)>> {
+        backend!(db self, pairs_of(annotator, id))
+    }
+
+    /// the flags with the given status, or all flags, newest first
+    pub(crate) async fn flags(&self, status: Option<&str>) -> Result<Vec<FlagRow>> {
+        backend!(db self, flags(status))
+    }
+
+    /// stores the answer to a gold pair
+    pub(crate) async fn insert_gold_answer(
+        &self,
+        m: &Match,
+        expected: f64,
+        score: f64,
+        correct: bool,
+    ) -> Result<()> {
+        backend!(db self, insert_gold_answer(m, expected, score, correct))
+    }
+
+    pub(crate) async fn matches_per_annotator(&self) -> Result<Vec<(String, i64)>> {
+        backend!(db self, matches_per_annotator())
+    }
+
+    /// answers and correct answers to gold pairs per annotator
+    pub(crate) async fn gold_answers_per_annotator(&self) -> Result<Vec<(String, i64, i64)>> {
+        backend!(db self, gold_answers_per_annotator())
+    }
+
+    /// the game weights set for the annotators
+    pub(crate) async fn annotator_weights(&self) -> Result<Vec<(String, f64)>> {
+        backend!(db self, annotator_weights())
+    }
+
+    /// annotator, home, guest and result of the rated matches of annotators, in their order
+    pub(crate) async fn judgements(&self) -> Result<Vec<(String, i64, i64, f64)>> {
+        backend!(db self, judgements())
+    }
+
+    pub(crate) async fn set_annotator_weight(&self, annotator: &str, weight: f64) -> Result<()> {
+        backend!(db self, set_annotator_weight(annotator, weight))
+    }
+}
+
+/// A transaction on either backend
+pub(crate) enum Tx {
+    Sqlite(sqlx::Transaction<'static, sqlx::Sqlite>),
+    Postgres(sqlx::Transaction<'static, sqlx::Postgres>),
+}
+
+impl Tx {
+    pub(crate) async fn begin(db: &Db) -> Result<Tx> {
+        Ok(match db {
+            Db::Sqlite(db) => Tx::Sqlite(db.begin().await?),
+            Db::Postgres(db) => Tx::Postgres(db.begin().await?),
+        })
+    }
+
+    pub(crate) async fn commit(self) -> Result<()> {
+        match self {
+            Tx::Sqlite(tx) => tx.commit().await?,
+            Tx::Postgres(tx) => tx.commit().await?,
+        }
+        Ok(())
+    }
+
+    /// adds an unrated player
+    pub(crate) async fn insert_player(&mut self, name: &str) -> Result<()> {
+        backend!(tx self, insert_player(name))
+    }
+
+    pub(crate) async fn delete_player(&mut self, name: &str) -> Result<()> {
+        backend!(tx self, delete_player(name))
+    }
+
+    pub(crate) async fn player_exists(&mut self, id: u32) -> Result<bool> {
+        backend!(tx self, player_exists(id))
+    }
+
+    /// the ratings of both players. They stay locked until the end of the transaction.
+    pub(crate) async fn ratings(&mut self, home_id: u32, guest_id: u32) -> Result<Vec<Rating>> {
+        backend!(tx self, ratings(home_id, guest_id))
+    }
+
+    pub(crate) async fn set_rating(&mut self, id: u32, rating: &glicko::Rating) -> Result<()> {
+        backend!(tx self, set_rating(id, rating.rating, rating.deviation))
+    }
+
+    /// home side and result of the last match of the annotator
+    pub(crate) async fn previous_match(
+        &mut self,
+        annotator: &str,
+    ) -> Result<Option<(Option<String>, Option<f64>)>> {
+        backend!(tx self, previous_match(annotator))
+    }
+
+    /// answers and correct answers of the annotator to gold pairs
+    pub(crate) async fn gold_accuracy(&mut self, annotator: &str) -> Result<(i64, i64)> {
+        backend!(tx self, gold_accuracy(annotator))
+    }
+
+    /// the game weight set for the annotator
+    pub(crate) async fn game_weight(&mut self, annotator: &str) -> Result<Option<f64>> {
+        backend!(tx self, game_weight(annotator))
+    }
+
+    /// inserts the match with the given timestamp, or the current time.
+    /// An excluded match keeps its result, but does not count as a game.
+    pub(crate) async fn insert_match(
+        &mut self,
+        m: &Match,
+        timestamp: Option<&str>,
+        excluded: bool,
+    ) -> Result<()> {
+        backend!(tx self, insert_match(m, timestamp, excluded))
+    }
+
+    /// opens a flag for the player and excludes it from the matchmaking
+    pub(crate) async fn insert_flag(
+        &mut self,
+        id: u32,
+        reason: FlagReason,
+        comment: Option<&str>,
+    ) -> Result<()> {
+        backend!(tx self, insert_flag(id, reason.as_str(), comment))
+    }
+
+    /// sets the flagged state of a flagged player and resolves its open flags with `status`.
+    /// Returns false if the player is not flagged.
+    pub(crate) async fn review_player(
+        &mut self,
+        id: u32,
+        flagged: bool,
+        status: &str,
+    ) -> Result<bool> {
+        backend!(tx self, review_player(id, flagged, status))
+    }
+
+    /// id, start, last activity and matches of the last session of the annotator
+    pub(crate) async fn latest_session(
+        &mut self,
+        annotator: &str,
+    ) -> Result<Option<(i64, i64, i64, i64)>> {
+        backend!(tx self, latest_session(annotator))
+    }
+
+    /// counts a match in the session
+    pub(crate) async fn continue_session(&mut self, id: i64, now: i64) -> Result<()> {
+        backend!(tx self, continue_session(id, now))
+    }
+
+    /// starts a session with one match and returns its id
+    pub(crate) async fn start_session(&mut self, annotator: &str, now: i64) -> Result<i64> {
+        backend!(tx self, start_session(annotator, now))
+    }
+}
+
+/// true, if the database url points to a PostgreSQL server
+pub fn is_postgres_url(url: &str) -> bool {
+    url.starts_with("postgres://") || url.starts_with("postgresql://")
+}
+
+impl Storage for Db {
+    async fn players(&self) -> Result<Vec<PlayerInfo>> {
+        let players = backend!(db self, players())?;
+        Ok(players.into_iter().map(PlayerInfo::from).collect())
+    }
+
+    async fn update_rating(&self, m: &Match, model: &RatingModel) -> Result<()> {
+        update_rating(self, m, model).await
+    }
+
+    async fn insert_match(&self, m: &Match) -> Result<()> {
+        record_unrated_match(self, m).await
+    }
+
+    async fn candidates(&self, count: usize, matchmaking: Matchmaking) -> Result<Vec<Duel>> {
+        calculate_new_matches(self, count, matchmaking).await
+    }
+}
+
+/// updates the ratings of both players and stores the match
+pub(crate) async fn update_rating(db: &Db, m: &Match, model: &RatingModel) -> Result<()> {
+    let mut tx = Tx::begin(db).await?;
+    update_rating_at(&mut tx, m, model, None).await?;
+    tx.commit().await
+}
+
+/// like [`update_rating`] inside of a running transaction. The match is stored with the given
+/// timestamp ("YYYY-MM-DD HH:MM") instead of the current time.
+pub(crate) async fn update_rating_at(
+    tx: &mut Tx,
+    m: &Match,
+    model: &RatingModel,
+    timestamp: Option<&str>,
+) -> Result<()> {
+    // the order bias comes from the previous duel of the same annotator
+    let previous = match &m.annotator {
+        Some(annotator) => tx.previous_match(annotator).await?,
+        None => None,
+    };
+    let previous_winner = previous.and_then(|(home_side, result)| {
+        winner_side(home_side.as_deref().and_then(|s| s.parse().ok()), result?)
+    });
+    // update the rating
+    let ratings = tx.ratings(m.home_id, m.guest_id).await?;
+    let rating_of = |id: u32| {
+        ratings
+            .iter()
+            .find(|r| r.id == id as i64)
+            .ok_or_else(|| anyhow::anyhow!("player {} does not exist", id))
+    };
+    let rt_home = rating_of(m.home_id)?;
+    let rt_guest = rating_of(m.guest_id)?;
+
+    let rth = glicko::Rating {
+        deviation: rt_home.deviation,
+        rating: rt_home.rating,
+        time: 0,
+    };
+    let rtg = glicko::Rating {
+        deviation: rt_guest.deviation,
+        rating: rt_guest.rating,
+        time: 0,
+    };
+    let weight = match (&model.quality_control, &m.annotator) {
+        (Some(qc), Some(annotator)) => annotator_weight(tx, qc, annotator).await?,
+        _ => 1_f64,
+    };
+    let game_weight = match &m.annotator {
+        Some(annotator) => tx.game_weight(annotator).await?.unwrap_or(1_f64),
+        None => 1_f64,
+    };
+    let Some((rth_new, rtg_new)) =
+        model.rate(&rth, &rtg, m, previous_winner, weight, game_weight)?
+    else {
+        info!("Results of annotator {:?} are excluded.", m.annotator);
+        return tx.insert_match(m, timestamp, true).await;
+    };
+    tx.set_rating(m.home_id, &rth_new).await?;
+    tx.set_rating(m.guest_id, &rtg_new).await?;
+
+    tx.insert_match(m, timestamp, false).await
+}
+
+/// weight of the results of an annotator by its accuracy on the gold pairs
+async fn annotator_weight(
+    tx: &mut Tx,
+    quality_control: &QualityControl,
+    annotator: &str,
+) -> Result<f64> {
+    let (answers, correct) = tx.gold_accuracy(annotator).await?;
+    Ok(quality_control.weight(answers as u64, correct as u64))
+}
+
+/// stores a match which does not change the ratings, like a skipped pair.
+/// A flagged player is excluded from the matchmaking until it gets reviewed.
+pub(crate) async fn record_unrated_match(db: &Db, m: &Match) -> Result<()> {
+    let mut tx = Tx::begin(db).await?;
+    tx.insert_match(m, None, false).await?;
+    if let Some(id) = m.flagged_player() {
+        tx.insert_flag(id, FlagReason::Other, None).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[derive(Clone, sqlx::FromRow)]
+pub(crate) struct Player {
+    pub(crate) id: i64,
+    pub(crate) rating: f64,
+    pub(crate) deviation: f64,
+    pub(crate) name: String,
+}
+
+/// pairs of player ids of all rated matches
+pub(crate) async fn judged_pairs(db: &Db) -> Result<Vec<(i64, i64)>> {
+    backend!(db db, judged_pairs())
+}
+
+async fn select_random_player_uniform(db: &Db, home_id: &Player) -> Result<Player> {
+    let all = backend!(db db, count_unflagged())?;
+    let rnd = {
+        let mut rng = rand::rng();
+        let distr = rand::distr::Uniform::new(0, all - 1)?;
+        rng.sample(distr)
+    };
+    backend!(db db, unflagged_player_at(home_id.id, rnd))
+}
+
+/// always the best performing strategy
+async fn select_random_player(db: &Db, home_id: &Player) -> Result<Player> {
+    // matchmaking
+    // todo: make some smulations of different matchmakings?
+    let upper = home_id.rating + 0.96 * home_id.deviation;
+    let lower = home_id.rating - 0.96 * home_id.deviation;
+
+    let random_id = backend!(db db, random_player_in_range(home_id.id, upper, lower));
+
+    match random_id {
+        Ok(r) => Ok(r),
+        _ => select_random_player_uniform(db, home_id).await,
+    }
+}
+
+pub(crate) async fn calculate_new_matches(
+    db: &Db,
+    n_matches: usize,
+    matchmaking: Matchmaking,
+) -> Result<Vec<Duel>> {
+    let mut duels = match matchmaking {
+        Matchmaking::Random => calculate_random_matches(db, n_matches).await?,
+        Matchmaking::Swiss => calculate_swiss_matches(db, n_matches).await?,
+    };
+    shuffle_sides(&mut duels);
+    Ok(duels)
+}
+
+pub(crate) async fn calculate_random_matches(db: &Db, n_matches: usize) -> Result<Vec<Duel>> {
+    let home_players = backend!(db db, most_uncertain_players(n_matches as u32))?;
+
+    let mut stream = tokio_stream::iter(home_players);
+    let mut result = Vec::new();
+    while let Some(home_id) = stream.next().await {
+        if let Ok(guest) = select_random_player(db, &home_id).await {
+            info!("Selected: {} - {}", home_id.name, guest.name);
+            result.push(Duel {
+                home: home_id.name,
+                home_id: home_id.id as u32,
+                guest: guest.name,
+                guest_id: guest.id as u32,
+            });
+        }
+    }
+    Ok(result)
+}
+
+pub(crate) async fn calculate_swiss_matches(db: &Db, n_matches: usize) -> Result<Vec<Duel>> {
+    let players = db.unflagged_players().await?;
+    let matches = judged_pairs(db).await?;
+    Ok(swiss_duels(&players, &matches, n_matches))
+}
+
+/// pairs the players by the Swiss system, given the pairs of the rated matches
+pub(crate) fn swiss_duels(
+    players: &[Player],
+    matches: &[(i64, i64)],
+    n_matches: usize,
+) -> Vec<Duel> {
+    let games = crate::count_games(matches.iter().copied());
+    let played = swiss::played_pairs(matches.iter().copied());
+    let entrants: Vec<swiss::Entrant> = players
+        .iter()
+        .map(|p| swiss::Entrant {
+            id: p.id,
+            rating: p.rating,
+            games: games.get(&p.id).copied().unwrap_or(0),
+        })
+        .collect();
+
+    swiss::pair(&entrants, &played, n_matches)
+        .into_iter()
+        .map(|(home, guest)| {
+            let (home, guest) = (&players[home], &players[guest]);
+            info!("Selected: {} - {}", home.name, guest.name);
+            Duel {
+                home: home.name.clone(),
+                home_id: home.id as u32,
+                guest: guest.name.clone(),
+                guest_id: guest.id as u32,
+            }
+        })
+        .collect()
+}
+
+/// the matchmaking puts the more uncertain player first,
+/// shuffle the sides, so clients do not show it always on the same side
+pub(crate) fn shuffle_sides(duels: &mut [Duel]) {
+    let mut rng = rand::rng();
+    for duel in duels.iter_mut() {
+        if rng.random_bool(0.5) {
+            std::mem::swap(&mut duel.home, &mut duel.guest);
+            std::mem::swap(&mut duel.home_id, &mut duel.guest_id);
+        }
+    }
+}
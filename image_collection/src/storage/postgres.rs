@@ -0,0 +1,551 @@
+//! Queries of the PostgreSQL backend. They mirror the ones of SQLite, ids are bound as BIGINT.
+
+use anyhow::Result;
+use sqlx::{PgConnection, PgPool};
+
+use super::{BiasRow, DuelRow, FlagRow, MatchRow, MatchSummary, Player, PlayerRow, Rating};
+use crate::{Match, migrations};
+
+/// opens the pool of the database and migrates it
+pub(super) async fn connect(url: &str) -> Result<PgPool> {
+    let db = sqlx::postgres::PgPoolOptions::new().connect(url).await?;
+    migrations::migrate_postgres(&db).await?;
+    Ok(db)
+}
+
+pub(super) async fn players(db: &PgPool) -> Result<Vec<PlayerRow>> {
+    Ok(sqlx::query_as(
+        "SELECT p.id, p.name, p.rating, p.deviation, p.flagged, COALESCE(g.games, 0) as games
+        FROM players p LEFT JOIN (
+            SELECT id, COUNT(*) as games FROM (
+                SELECT home_players_id as id FROM matches WHERE result IS NOT NULL AND NOT excluded
+                UNION ALL
+                SELECT guest_players_id as id FROM matches WHERE result IS NOT NULL AND NOT excluded) ids
+            GROUP BY id) g ON g.id = p.id
+        ORDER BY p.id",
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn player_page(
+    db: &PgPool,
+    order_by: &str,
+    direction: i64,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<PlayerRow>> {
+    Ok(sqlx::query_as(
+        "SELECT p.id, p.name, p.rating, p.deviation, p.flagged, COALESCE(g.games, 0) as games
+        FROM players p LEFT JOIN (
+            SELECT id, COUNT(*) as games FROM (
+                SELECT home_players_id as id FROM matches WHERE result IS NOT NULL AND NOT excluded
+                UNION ALL
+                SELECT guest_players_id as id FROM matches WHERE result IS NOT NULL AND NOT excluded) ids
+            GROUP BY id) g ON g.id = p.id
+        ORDER BY (CASE $1
+            WHEN 'deviation' THEN p.deviation
+            WHEN 'games' THEN COALESCE(g.games, 0)
+            ELSE p.rating END) * $2, p.id
+        LIMIT $3 OFFSET $4",
+    )
+    .bind(order_by)
+    .bind(direction as f64)
+    .bind(limit as i64)
+    .bind(offset as i64)
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn count_players(db: &PgPool) -> Result<i64> {
+    Ok(sqlx::query_scalar("SELECT COUNT(*) FROM players")
+        .fetch_one(db)
+        .await?)
+}
+
+pub(super) async fn player_names(db: &PgPool) -> Result<Vec<String>> {
+    Ok(sqlx::query_scalar("SELECT name FROM players")
+        .fetch_all(db)
+        .await?)
+}
+
+pub(super) async fn player_id(db: &PgPool, name: &str) -> Result<Option<i64>> {
+    Ok(sqlx::query_scalar("SELECT id FROM players WHERE name = $1")
+        .bind(name)
+        .fetch_optional(db)
+        .await?)
+}
+
+pub(super) async fn flagged_players(db: &PgPool) -> Result<Vec<i64>> {
+    Ok(sqlx::query_scalar("SELECT id FROM players WHERE flagged")
+        .fetch_all(db)
+        .await?)
+}
+
+pub(super) async fn count_flagged(db: &PgPool) -> Result<i64> {
+    Ok(
+        sqlx::query_scalar("SELECT COUNT(*) FROM players WHERE flagged")
+            .fetch_one(db)
+            .await?,
+    )
+}
+
+pub(super) async fn count_unflagged(db: &PgPool) -> Result<i64> {
+    Ok(
+        sqlx::query_scalar("SELECT COUNT(*) FROM players WHERE NOT flagged")
+            .fetch_one(db)
+            .await?,
+    )
+}
+
+pub(super) async fn unflagged_players(db: &PgPool) -> Result<Vec<Player>> {
+    Ok(
+        sqlx::query_as("SELECT id, rating, deviation, name FROM players WHERE NOT flagged")
+            .fetch_all(db)
+            .await?,
+    )
+}
+
+pub(super) async fn unflagged_ratings(db: &PgPool) -> Result<Vec<(f64, f64)>> {
+    Ok(
+        sqlx::query_as("SELECT rating, deviation FROM players WHERE NOT flagged")
+            .fetch_all(db)
+            .await?,
+    )
+}
+
+pub(super) async fn unflagged_names(db: &PgPool, a: i64, b: i64) -> Result<Vec<(i64, String)>> {
+    Ok(
+        sqlx::query_as("SELECT id, name FROM players WHERE id IN ($1, $2) AND NOT flagged")
+            .bind(a)
+            .bind(b)
+            .fetch_all(db)
+            .await?,
+    )
+}
+
+pub(super) async fn most_uncertain_players(db: &PgPool, count: u32) -> Result<Vec<Player>> {
+    Ok(sqlx::query_as(
+        "SELECT id, rating, deviation, name FROM players
+        WHERE NOT flagged
+        ORDER BY deviation DESC
+        LIMIT $1",
+    )
+    .bind(count as i64)
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn unflagged_player_at(db: &PgPool, excluded: i64, offset: i64) -> Result<Player> {
+    Ok(sqlx::query_as(
+        "SELECT id, rating, deviation, name FROM players
+        WHERE id != $1 AND NOT flagged LIMIT 1 OFFSET $2",
+    )
+    .bind(excluded)
+    .bind(offset)
+    .fetch_one(db)
+    .await?)
+}
+
+pub(super) async fn random_player_in_range(
+    db: &PgPool,
+    excluded: i64,
+    upper: f64,
+    lower: f64,
+) -> Result<Player> {
+    Ok(sqlx::query_as(
+        "SELECT id, rating, deviation, name FROM players
+            WHERE id != $1 AND NOT flagged AND
+            rating <= $2 AND
+            rating >= $3
+            ORDER BY id
+            LIMIT 1 OFFSET floor(random() * (SELECT COUNT(*)
+                FROM players WHERE id != $1 AND NOT flagged AND
+                rating <= $2 AND
+                rating >= $3))::BIGINT",
+    )
+    .bind(excluded)
+    .bind(upper)
+    .bind(lower)
+    .fetch_one(db)
+    .await?)
+}
+
+pub(super) async fn recent_matches(db: &PgPool, limit: u32) -> Result<Vec<MatchRow>> {
+    Ok(sqlx::query_as(
+        "SELECT m.id, m.home_players_id, h.name as home, m.guest_players_id, g.name as guest,
+            m.result, m.outcome, m.timestamp, m.home_side, m.response_ms, m.annotator, m.session_id
+        FROM matches m
+        JOIN players h ON h.id = m.home_players_id
+        JOIN players g ON g.id = m.guest_players_id
+        ORDER BY m.id DESC
+        LIMIT $1",
+    )
+    .bind(limit as i64)
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn activity(db: &PgPool, hours: u32) -> Result<Vec<(String, i64)>> {
+    Ok(sqlx::query_as(
+        "SELECT substr(timestamp, 1, 13) || ':00', COUNT(*)
+        FROM matches
+        WHERE timestamp >= to_char(now() AT TIME ZONE 'UTC' - make_interval(hours => $1), 'YYYY-MM-DD HH24:00')
+        GROUP BY 1
+        ORDER BY 1",
+    )
+    .bind(hours as i32)
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn count_rated_matches(db: &PgPool) -> Result<i64> {
+    Ok(
+        sqlx::query_scalar(
+            "SELECT COUNT(*) FROM matches WHERE result IS NOT NULL AND NOT excluded",
+        )
+        .fetch_one(db)
+        .await?,
+    )
+}
+
+pub(super) async fn match_summary(db: &PgPool) -> Result<MatchSummary> {
+    Ok(sqlx::query_as(
+        "SELECT COUNT(result) as matches,
+            COUNT(*) FILTER (WHERE outcome = 'skip') as skipped,
+            COUNT(*) FILTER (WHERE result = 0.5) as draws,
+            COALESCE(EXTRACT(EPOCH FROM MAX(timestamp)::timestamp - MIN(timestamp)::timestamp) / 3600, 0)::DOUBLE PRECISION as hours,
+            COUNT(*) FILTER (WHERE result IS NOT NULL AND timestamp >= to_char(now() AT TIME ZONE 'UTC' - interval '1 hour', 'YYYY-MM-DD HH24:MI')) as last_hour,
+            COUNT(*) FILTER (WHERE home_side IS NOT NULL AND result != 0.5) as sided,
+            COUNT(*) FILTER (WHERE (home_side = 'left' AND result > 0.5) OR (home_side = 'right' AND result < 0.5)) as left_wins
+        FROM matches WHERE NOT excluded",
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+pub(super) async fn judged_pairs(db: &PgPool) -> Result<Vec<(i64, i64)>> {
+    Ok(sqlx::query_as(
+        "SELECT home_players_id, guest_players_id FROM matches WHERE result IS NOT NULL AND NOT excluded",
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn bias_rows(db: &PgPool) -> Result<Vec<BiasRow>> {
+    Ok(sqlx::query_as(
+        "SELECT m.home_side, m.result, m.annotator, h.rating - g.rating as rating_diff
+        FROM matches m
+        JOIN players h ON h.id = m.home_players_id
+        JOIN players g ON g.id = m.guest_players_id
+        ORDER BY m.id",
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn repeated_pair(db: &PgPool, annotator: &str) -> Result<Option<DuelRow>> {
+    Ok(sqlx::query_as(
+        "SELECT h.id as home_id, h.name as home, g.id as guest_id, g.name as guest
+        FROM matches m
+        JOIN players h ON h.id = m.home_players_id
+        JOIN players g ON g.id = m.guest_players_id
+        WHERE m.result IS NOT NULL AND m.annotator = $1 AND NOT h.flagged AND NOT g.flagged
+        ORDER BY RANDOM() LIMIT 1",
+    )
+    .bind(annotator)
+    .fetch_optional(db)
+    .await?)
+}
+
+pub(super) async fn random_pair(db: &PgPool, annotator: &str) -> Result<Option<(i64, i64)>> {
+    Ok(sqlx::query_as(
+        "SELECT home_players_id, guest_players_id FROM matches
+        WHERE result IS NOT NULL AND annotator = $1 ORDER BY RANDOM() LIMIT 1",
+    )
+    .bind(annotator)
+    .fetch_optional(db)
+    .await?)
+}
+
+pub(super) async fn pairs_of(db: &PgPool, annotator: &str, id: i64) -> Result<Vec<(i64, i64)>> {
+    Ok(sqlx::query_as(
+        "SELECT home_players_id, guest_players_id FROM matches
+        WHERE result IS NOT NULL AND annotator = $2
+            AND (home_players_id = $1 OR guest_players_id = $1)",
+    )
+    .bind(id)
+    .bind(annotator)
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn flags(db: &PgPool, status: Option<&str>) -> Result<Vec<FlagRow>> {
+    Ok(sqlx::query_as(
+        "SELECT f.id, f.players_id, p.name, f.reason, f.comment, f.timestamp, f.status
+        FROM flags f
+        JOIN players p ON p.id = f.players_id
+        WHERE $1 IS NULL OR f.status = $1
+        ORDER BY f.id DESC",
+    )
+    .bind(status)
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn insert_gold_answer(
+    db: &PgPool,
+    m: &Match,
+    expected: f64,
+    score: f64,
+    correct: bool,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO gold_answers (home_players_id, guest_players_id, expected, result, correct, annotator, timestamp, response_ms)
+        VALUES ($1, $2, $3, $4, $5, $6, to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI'), $7)",
+    )
+    .bind(m.home_id as i64)
+    .bind(m.guest_id as i64)
+    .bind(expected)
+    .bind(score)
+    .bind(correct)
+    .bind(&m.annotator)
+    .bind(m.response_ms.map(i64::from))
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn matches_per_annotator(db: &PgPool) -> Result<Vec<(String, i64)>> {
+    Ok(sqlx::query_as(
+        "SELECT annotator, COUNT(*)
+        FROM matches WHERE annotator IS NOT NULL GROUP BY annotator",
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn gold_answers_per_annotator(db: &PgPool) -> Result<Vec<(String, i64, i64)>> {
+    Ok(sqlx::query_as(
+        "SELECT annotator, COUNT(*), COUNT(*) FILTER (WHERE correct)
+        FROM gold_answers WHERE annotator IS NOT NULL GROUP BY annotator",
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn annotator_weights(db: &PgPool) -> Result<Vec<(String, f64)>> {
+    Ok(sqlx::query_as("SELECT name, weight FROM annotators")
+        .fetch_all(db)
+        .await?)
+}
+
+pub(super) async fn judgements(db: &PgPool) -> Result<Vec<(String, i64, i64, f64)>> {
+    Ok(sqlx::query_as(
+        "SELECT annotator, home_players_id, guest_players_id, result
+        FROM matches WHERE annotator IS NOT NULL AND result IS NOT NULL
+        ORDER BY id",
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn set_annotator_weight(db: &PgPool, annotator: &str, weight: f64) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO annotators (name, weight) VALUES ($1, $2)
+        ON CONFLICT (name) DO UPDATE SET weight = excluded.weight",
+    )
+    .bind(annotator)
+    .bind(weight)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn insert_player(tx: &mut PgConnection, name: &str) -> Result<()> {
+    sqlx::query("INSERT INTO players (name, rating, deviation) VALUES ($1, 2200, 350)")
+        .bind(name)
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn delete_player(tx: &mut PgConnection, name: &str) -> Result<()> {
+    sqlx::query("DELETE FROM players WHERE name = $1")
+        .bind(name)
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn player_exists(tx: &mut PgConnection, id: u32) -> Result<bool> {
+    Ok(
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM players WHERE id = $1")
+            .bind(id as i64)
+            .fetch_one(tx)
+            .await?
+            > 0,
+    )
+}
+
+/// concurrent updates of the same players wait for each other.
+/// The rows are locked in the order of their id to avoid deadlocks.
+pub(super) async fn ratings(
+    tx: &mut PgConnection,
+    home_id: u32,
+    guest_id: u32,
+) -> Result<Vec<Rating>> {
+    Ok(sqlx::query_as(
+        "SELECT id, rating, deviation FROM players WHERE id IN ($1, $2)
+        ORDER BY id FOR UPDATE",
+    )
+    .bind(home_id as i64)
+    .bind(guest_id as i64)
+    .fetch_all(tx)
+    .await?)
+}
+
+pub(super) async fn set_rating(
+    tx: &mut PgConnection,
+    id: u32,
+    rating: f64,
+    deviation: f64,
+) -> Result<()> {
+    sqlx::query("UPDATE players SET rating = $1, deviation = $2 WHERE id = $3")
+        .bind(rating)
+        .bind(deviation)
+        .bind(id as i64)
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn previous_match(
+    tx: &mut PgConnection,
+    annotator: &str,
+) -> Result<Option<(Option<String>, Option<f64>)>> {
+    Ok(sqlx::query_as(
+        "SELECT home_side, result FROM matches WHERE annotator = $1 ORDER BY id DESC LIMIT 1",
+    )
+    .bind(annotator)
+    .fetch_optional(tx)
+    .await?)
+}
+
+pub(super) async fn gold_accuracy(tx: &mut PgConnection, annotator: &str) -> Result<(i64, i64)> {
+    Ok(sqlx::query_as(
+        "SELECT COUNT(*), COUNT(*) FILTER (WHERE correct)
+        FROM gold_answers WHERE annotator = $1",
+    )
+    .bind(annotator)
+    .fetch_one(tx)
+    .await?)
+}
+
+pub(super) async fn game_weight(tx: &mut PgConnection, annotator: &str) -> Result<Option<f64>> {
+    Ok(
+        sqlx::query_scalar("SELECT weight FROM annotators WHERE name = $1")
+            .bind(annotator)
+            .fetch_optional(tx)
+            .await?,
+    )
+}
+
+pub(super) async fn insert_match(
+    tx: &mut PgConnection,
+    m: &Match,
+    timestamp: Option<&str>,
+    excluded: bool,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO matches (home_players_id, guest_players_id, result, timestamp, home_side, response_ms, outcome, annotator, session_id, excluded) VALUES ($1, $2, $3, COALESCE($9, to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI')), $4, $5, $6, $7, $8, $10)",
+    )
+    .bind(m.home_id as i64)
+    .bind(m.guest_id as i64)
+    .bind(m.won.score())
+    .bind(m.home_side.map(|s| s.as_str()))
+    .bind(m.response_ms.map(i64::from))
+    .bind(m.won.label())
+    .bind(&m.annotator)
+    .bind(m.session_id)
+    .bind(timestamp)
+    .bind(excluded)
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn insert_flag(
+    tx: &mut PgConnection,
+    id: u32,
+    reason: &str,
+    comment: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO flags (players_id, reason, comment, timestamp) VALUES ($1, $2, $3, to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI'))",
+    )
+    .bind(id as i64)
+    .bind(reason)
+    .bind(comment)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("UPDATE players SET flagged = TRUE WHERE id = $1")
+        .bind(id as i64)
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn review_player(
+    tx: &mut PgConnection,
+    id: u32,
+    flagged: bool,
+    status: &str,
+) -> Result<bool> {
+    let updated = sqlx::query("UPDATE players SET flagged = $1 WHERE id = $2 AND flagged")
+        .bind(flagged)
+        .bind(id as i64)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if updated == 0 {
+        return Ok(false);
+    }
+    sqlx::query("UPDATE flags SET status = $1 WHERE players_id = $2 AND status = 'open'")
+        .bind(status)
+        .bind(id as i64)
+        .execute(tx)
+        .await?;
+    Ok(true)
+}
+
+pub(super) async fn latest_session(
+    tx: &mut PgConnection,
+    annotator: &str,
+) -> Result<Option<(i64, i64, i64, i64)>> {
+    Ok(sqlx::query_as(
+        "SELECT id, started, last_active, matches FROM sessions WHERE annotator = $1 ORDER BY id DESC LIMIT 1",
+    )
+    .bind(annotator)
+    .fetch_optional(tx)
+    .await?)
+}
+
+pub(super) async fn continue_session(tx: &mut PgConnection, id: i64, now: i64) -> Result<()> {
+    sqlx::query("UPDATE sessions SET last_active = $1, matches = matches + 1 WHERE id = $2")
+        .bind(now)
+        .bind(id)
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn start_session(tx: &mut PgConnection, annotator: &str, now: i64) -> Result<i64> {
+    Ok(sqlx::query_scalar(
+        "INSERT INTO sessions (annotator, started, last_active, matches) VALUES ($1, $2, $2, 1) RETURNING id",
+    )
+    .bind(annotator)
+    .bind(now)
+    .fetch_one(tx)
+    .await?)
+}
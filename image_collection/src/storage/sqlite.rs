@@ -0,0 +1,680 @@
+//! Queries of the SQLite backend. They are checked at compile time against `DATABASE_URL`.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqliteLockingMode, SqlitePoolOptions,
+    SqliteSynchronous,
+};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use super::{BiasRow, DuelRow, FlagRow, MatchRow, MatchSummary, Player, PlayerRow, Rating};
+use crate::{Match, migrations};
+
+/// opens the writer and the reader pool of the database and migrates it.
+/// Without read connections, the reader is the writer.
+pub(super) async fn connect(
+    path: &str,
+    read_connections: Option<u32>,
+) -> Result<(SqlitePool, SqlitePool)> {
+    let locking_mode = match read_connections {
+        Some(_) => SqliteLockingMode::Normal,
+        None => SqliteLockingMode::Exclusive,
+    };
+    let db_opions = SqliteConnectOptions::from_str(path)?
+        .shared_cache(false)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(std::time::Duration::from_secs(5000))
+        .locking_mode(locking_mode)
+        .journal_mode(SqliteJournalMode::Wal)
+        .foreign_keys(true)
+        .pragma("temp_store", "MEMORY")
+        .pragma("mmap_size", "134217728")
+        .pragma("journal_size_limit", "67108864")
+        .pragma("cache_size", "64000")
+        .create_if_missing(true);
+
+    let db = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(db_opions.clone())
+        .await?;
+    migrations::migrate(&db).await?;
+    // the readers are opened after the migration, which creates the database
+    let reader = match read_connections {
+        Some(connections) => {
+            SqlitePoolOptions::new()
+                .max_connections(std::cmp::max(1, connections))
+                .connect_with(db_opions.read_only(true).create_if_missing(false))
+                .await?
+        }
+        None => db.clone(),
+    };
+    Ok((db, reader))
+}
+
+/// opens an empty, migrated database in memory
+pub(super) async fn in_memory() -> Result<SqlitePool> {
+    let db_opions = SqliteConnectOptions::from_str(":memory:")?
+        .shared_cache(false)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(std::time::Duration::from_secs(3000))
+        .locking_mode(SqliteLockingMode::Exclusive)
+        .journal_mode(SqliteJournalMode::Wal)
+        .foreign_keys(true)
+        .pragma("temp_store", "MEMORY")
+        .pragma("mmap_size", "134217728")
+        .pragma("journal_size_limit", "67108864")
+        .pragma("cache_size", "64000");
+
+    let db = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(db_opions)
+        .await?;
+    migrations::migrate(&db).await?;
+    Ok(db)
+}
+
+pub(super) async fn players(db: &SqlitePool) -> Result<Vec<PlayerRow>> {
+    Ok(sqlx::query_as!(
+        PlayerRow,
+        r#"SELECT p.id, p.name, p.rating, p.deviation, p.flagged, COALESCE(g.games, 0) as "games!: i64"
+        FROM players p LEFT JOIN (
+            SELECT id, COUNT(*) as games FROM (
+                SELECT home_players_id as id FROM matches WHERE result IS NOT NULL AND NOT excluded
+                UNION ALL
+                SELECT guest_players_id as id FROM matches WHERE result IS NOT NULL AND NOT excluded)
+            GROUP BY id) g ON g.id = p.id
+        ORDER BY p.id"#
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn player_page(
+    db: &SqlitePool,
+    order_by: &str,
+    direction: i64,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<PlayerRow>> {
+    Ok(sqlx::query_as!(
+        PlayerRow,
+        r#"SELECT p.id, p.name, p.rating, p.deviation, p.flagged, COALESCE(g.games, 0) as "games!: i64"
+        FROM players p LEFT JOIN (
+            SELECT id, COUNT(*) as games FROM (
+                SELECT home_players_id as id FROM matches WHERE result IS NOT NULL AND NOT excluded
+                UNION ALL
+                SELECT guest_players_id as id FROM matches WHERE result IS NOT NULL AND NOT excluded)
+            GROUP BY id) g ON g.id = p.id
+        ORDER BY (CASE $1
+            WHEN 'deviation' THEN p.deviation
+            WHEN 'games' THEN COALESCE(g.games, 0)
+            ELSE p.rating END) * $2, p.id
+        LIMIT $3 OFFSET $4"#,
+        order_by,
+        direction,
+        limit,
+        offset
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn count_players(db: &SqlitePool) -> Result<i64> {
+    Ok(sqlx::query_scalar!("SELECT COUNT(*) FROM players")
+        .fetch_one(db)
+        .await?)
+}
+
+pub(super) async fn player_names(db: &SqlitePool) -> Result<Vec<String>> {
+    Ok(sqlx::query_scalar!("SELECT name FROM players")
+        .fetch_all(db)
+        .await?)
+}
+
+pub(super) async fn player_id(db: &SqlitePool, name: &str) -> Result<Option<i64>> {
+    Ok(
+        sqlx::query_scalar!("SELECT id FROM players WHERE name = ?", name)
+            .fetch_optional(db)
+            .await?,
+    )
+}
+
+pub(super) async fn flagged_players(db: &SqlitePool) -> Result<Vec<i64>> {
+    Ok(sqlx::query_scalar!("SELECT id FROM players WHERE flagged")
+        .fetch_all(db)
+        .await?)
+}
+
+pub(super) async fn count_flagged(db: &SqlitePool) -> Result<i64> {
+    Ok(
+        sqlx::query_scalar!("SELECT COUNT(*) FROM players WHERE flagged")
+            .fetch_one(db)
+            .await?,
+    )
+}
+
+pub(super) async fn count_unflagged(db: &SqlitePool) -> Result<i64> {
+    Ok(
+        sqlx::query_scalar!("SELECT COUNT(*) FROM players WHERE NOT flagged")
+            .fetch_one(db)
+            .await?,
+    )
+}
+
+pub(super) async fn unflagged_players(db: &SqlitePool) -> Result<Vec<Player>> {
+    Ok(sqlx::query_as!(
+        Player,
+        "SELECT id, rating, deviation, name FROM players WHERE NOT flagged"
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn unflagged_ratings(db: &SqlitePool) -> Result<Vec<(f64, f64)>> {
+    Ok(
+        sqlx::query!("SELECT rating, deviation FROM players WHERE NOT flagged")
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|r| (r.rating, r.deviation))
+            .collect(),
+    )
+}
+
+pub(super) async fn unflagged_names(db: &SqlitePool, a: i64, b: i64) -> Result<Vec<(i64, String)>> {
+    Ok(sqlx::query!(
+        "SELECT id, name FROM players WHERE id IN (?, ?) AND NOT flagged",
+        a,
+        b
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|p| (p.id, p.name))
+    .collect())
+}
+
+pub(super) async fn most_uncertain_players(db: &SqlitePool, count: u32) -> Result<Vec<Player>> {
+    Ok(sqlx::query_as!(
+        Player,
+        "SELECT id, rating, deviation, name FROM players
+                WHERE NOT flagged
+                ORDER BY deviation DESC
+                LIMIT ?",
+        count
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn unflagged_player_at(
+    db: &SqlitePool,
+    excluded: i64,
+    offset: i64,
+) -> Result<Player> {
+    Ok(sqlx::query_as!(
+        Player,
+        "SELECT id, rating, deviation, name FROM players
+        WHERE id != $1 AND NOT flagged LIMIT 1 OFFSET $2",
+        excluded,
+        offset
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+pub(super) async fn random_player_in_range(
+    db: &SqlitePool,
+    excluded: i64,
+    upper: f64,
+    lower: f64,
+) -> Result<Player> {
+    Ok(sqlx::query_as!(
+        Player,
+        "SELECT id as \"id!\", rating, deviation, name FROM players
+            WHERE id IN (SELECT id FROM players
+                WHERE id != $1 AND NOT flagged AND
+                rating <= $2 AND
+                rating >= $3
+                LIMIT 1 OFFSET (ABS(RANDOM()) % (SELECT COUNT(*)
+                    FROM players WHERE id != $1 AND NOT flagged AND
+                    rating <= $2 AND
+                    rating >= $3))
+                )",
+        excluded,
+        upper,
+        lower
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+pub(super) async fn recent_matches(db: &SqlitePool, limit: u32) -> Result<Vec<MatchRow>> {
+    Ok(sqlx::query_as!(
+        MatchRow,
+        r#"SELECT m.id, m.home_players_id, h.name as home, m.guest_players_id, g.name as guest,
+            m.result, m.outcome, m.timestamp, m.home_side, m.response_ms, m.annotator, m.session_id
+        FROM matches m
+        JOIN players h ON h.id = m.home_players_id
+        JOIN players g ON g.id = m.guest_players_id
+        ORDER BY m.id DESC
+        LIMIT ?"#,
+        limit
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn activity(db: &SqlitePool, hours: u32) -> Result<Vec<(String, i64)>> {
+    let since = format!("-{hours} hours");
+    Ok(sqlx::query!(
+        r#"SELECT substr(timestamp, 1, 13) || ':00' as "hour!: String", COUNT(*) as "matches!: i64"
+        FROM matches
+        WHERE timestamp >= strftime('%Y-%m-%d %H:00', 'now', ?)
+        GROUP BY 1
+        ORDER BY 1"#,
+        since
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|a| (a.hour, a.matches))
+    .collect())
+}
+
+pub(super) async fn count_rated_matches(db: &SqlitePool) -> Result<i64> {
+    Ok(sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM matches WHERE result IS NOT NULL AND NOT excluded"
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+pub(super) async fn match_summary(db: &SqlitePool) -> Result<MatchSummary> {
+    Ok(sqlx::query_as!(
+        MatchSummary,
+        r#"SELECT COUNT(result) as "matches!: i64",
+            COALESCE(SUM(outcome = 'skip'), 0) as "skipped!: i64",
+            COALESCE(SUM(result = 0.5), 0) as "draws!: i64",
+            COALESCE((julianday(MAX(timestamp)) - julianday(MIN(timestamp))) * 24, 0) as "hours!: f64",
+            COALESCE(SUM(result IS NOT NULL AND timestamp >= strftime('%Y-%m-%d %H:%M', 'now', '-1 hour')), 0) as "last_hour!: i64",
+            COALESCE(SUM(home_side IS NOT NULL AND result != 0.5), 0) as "sided!: i64",
+            COALESCE(SUM((home_side = 'left' AND result > 0.5) OR (home_side = 'right' AND result < 0.5)), 0) as "left_wins!: i64"
+        FROM matches WHERE NOT excluded"#
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+pub(super) async fn judged_pairs(db: &SqlitePool) -> Result<Vec<(i64, i64)>> {
+    Ok(sqlx::query!(
+        "SELECT home_players_id, guest_players_id FROM matches WHERE result IS NOT NULL AND NOT excluded"
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|m| (m.home_players_id, m.guest_players_id))
+    .collect())
+}
+
+pub(super) async fn bias_rows(db: &SqlitePool) -> Result<Vec<BiasRow>> {
+    Ok(sqlx::query_as!(
+        BiasRow,
+        r#"SELECT m.home_side, m.result, m.annotator, h.rating - g.rating as "rating_diff!: f64"
+        FROM matches m
+        JOIN players h ON h.id = m.home_players_id
+        JOIN players g ON g.id = m.guest_players_id
+        ORDER BY m.id"#
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn repeated_pair(db: &SqlitePool, annotator: &str) -> Result<Option<DuelRow>> {
+    Ok(sqlx::query_as!(
+        DuelRow,
+        "SELECT h.id as home_id, h.name as home, g.id as guest_id, g.name as guest
+        FROM matches m
+        JOIN players h ON h.id = m.home_players_id
+        JOIN players g ON g.id = m.guest_players_id
+        WHERE m.result IS NOT NULL AND m.annotator = ? AND NOT h.flagged AND NOT g.flagged
+        ORDER BY RANDOM() LIMIT 1",
+        annotator
+    )
+    .fetch_optional(db)
+    .await?)
+}
+
+pub(super) async fn random_pair(db: &SqlitePool, annotator: &str) -> Result<Option<(i64, i64)>> {
+    Ok(sqlx::query!(
+        "SELECT home_players_id, guest_players_id FROM matches
+        WHERE result IS NOT NULL AND annotator = ? ORDER BY RANDOM() LIMIT 1",
+        annotator
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|r| (r.home_players_id, r.guest_players_id)))
+}
+
+pub(super) async fn pairs_of(db: &SqlitePool, annotator: &str, id: i64) -> Result<Vec<(i64, i64)>> {
+    Ok(sqlx::query!(
+        "SELECT home_players_id, guest_players_id FROM matches
+        WHERE result IS NOT NULL AND annotator = ?2
+            AND (home_players_id = ?1 OR guest_players_id = ?1)",
+        id,
+        annotator
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|r| (r.home_players_id, r.guest_players_id))
+    .collect())
+}
+
+pub(super) async fn flags(db: &SqlitePool, status: Option<&str>) -> Result<Vec<FlagRow>> {
+    Ok(sqlx::query_as!(
+        FlagRow,
+        r#"SELECT f.id, f.players_id, p.name, f.reason, f.comment, f.timestamp, f.status
+        FROM flags f
+        JOIN players p ON p.id = f.players_id
+        WHERE $1 IS NULL OR f.status = $1
+        ORDER BY f.id DESC"#,
+        status
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+pub(super) async fn insert_gold_answer(
+    db: &SqlitePool,
+    m: &Match,
+    expected: f64,
+    score: f64,
+    correct: bool,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO gold_answers (home_players_id, guest_players_id, expected, result, correct, annotator, timestamp, response_ms)
+        VALUES (?, ?, ?, ?, ?, ?, strftime('%Y-%m-%d %H:%M','now'), ?)",
+        m.home_id,
+        m.guest_id,
+        expected,
+        score,
+        correct,
+        m.annotator,
+        m.response_ms
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn matches_per_annotator(db: &SqlitePool) -> Result<Vec<(String, i64)>> {
+    Ok(sqlx::query!(
+        r#"SELECT annotator as "annotator!: String", COUNT(*) as "matches!: i64"
+        FROM matches WHERE annotator IS NOT NULL GROUP BY annotator"#
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|m| (m.annotator, m.matches))
+    .collect())
+}
+
+pub(super) async fn gold_answers_per_annotator(db: &SqlitePool) -> Result<Vec<(String, i64, i64)>> {
+    Ok(sqlx::query!(
+        r#"SELECT annotator as "annotator!: String", COUNT(*) as "answers!: i64", SUM(correct) as "correct!: i64"
+        FROM gold_answers WHERE annotator IS NOT NULL GROUP BY annotator"#
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|g| (g.annotator, g.answers, g.correct))
+    .collect())
+}
+
+pub(super) async fn annotator_weights(db: &SqlitePool) -> Result<Vec<(String, f64)>> {
+    Ok(sqlx::query!("SELECT name, weight FROM annotators")
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|w| (w.name, w.weight))
+        .collect())
+}
+
+pub(super) async fn judgements(db: &SqlitePool) -> Result<Vec<(String, i64, i64, f64)>> {
+    Ok(sqlx::query!(
+        r#"SELECT annotator as "annotator!: String", home_players_id, guest_players_id, result as "result!: f64"
+        FROM matches WHERE annotator IS NOT NULL AND result IS NOT NULL
+        ORDER BY id"#
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|j| (j.annotator, j.home_players_id, j.guest_players_id, j.result))
+    .collect())
+}
+
+pub(super) async fn set_annotator_weight(
+    db: &SqlitePool,
+    annotator: &str,
+    weight: f64,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO annotators (name, weight) VALUES (?, ?)
+        ON CONFLICT (name) DO UPDATE SET weight = excluded.weight",
+        annotator,
+        weight
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn insert_player(tx: &mut SqliteConnection, name: &str) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO players (name, rating, deviation) VALUES (?, 2200, 350)",
+        name
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn delete_player(tx: &mut SqliteConnection, name: &str) -> Result<()> {
+    sqlx::query!("DELETE FROM players WHERE name = ?", name)
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn player_exists(tx: &mut SqliteConnection, id: u32) -> Result<bool> {
+    Ok(
+        sqlx::query_scalar!("SELECT COUNT(*) FROM players WHERE id = ?", id)
+            .fetch_one(tx)
+            .await?
+            > 0,
+    )
+}
+
+pub(super) async fn ratings(
+    tx: &mut SqliteConnection,
+    home_id: u32,
+    guest_id: u32,
+) -> Result<Vec<Rating>> {
+    Ok(sqlx::query_as!(
+        Rating,
+        "SELECT id, rating, deviation FROM players WHERE id IN (?, ?)",
+        home_id,
+        guest_id
+    )
+    .fetch_all(tx)
+    .await?)
+}
+
+pub(super) async fn set_rating(
+    tx: &mut SqliteConnection,
+    id: u32,
+    rating: f64,
+    deviation: f64,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE players SET rating = ?, deviation = ? WHERE id = ?",
+        rating,
+        deviation,
+        id
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn previous_match(
+    tx: &mut SqliteConnection,
+    annotator: &str,
+) -> Result<Option<(Option<String>, Option<f64>)>> {
+    Ok(sqlx::query!(
+        "SELECT home_side, result FROM matches WHERE annotator = ? ORDER BY id DESC LIMIT 1",
+        annotator
+    )
+    .fetch_optional(tx)
+    .await?
+    .map(|p| (p.home_side, p.result)))
+}
+
+pub(super) async fn gold_accuracy(
+    tx: &mut SqliteConnection,
+    annotator: &str,
+) -> Result<(i64, i64)> {
+    let accuracy = sqlx::query!(
+        r#"SELECT COUNT(*) as "answers!: i64", COALESCE(SUM(correct), 0) as "correct!: i64"
+        FROM gold_answers WHERE annotator = ?"#,
+        annotator
+    )
+    .fetch_one(tx)
+    .await?;
+    Ok((accuracy.answers, accuracy.correct))
+}
+
+pub(super) async fn game_weight(tx: &mut SqliteConnection, annotator: &str) -> Result<Option<f64>> {
+    Ok(
+        sqlx::query_scalar!("SELECT weight FROM annotators WHERE name = ?", annotator)
+            .fetch_optional(tx)
+            .await?,
+    )
+}
+
+pub(super) async fn insert_match(
+    tx: &mut SqliteConnection,
+    m: &Match,
+    timestamp: Option<&str>,
+    excluded: bool,
+) -> Result<()> {
+    let result = m.won.score();
+    let outcome = m.won.label();
+    let home_side = m.home_side.map(|s| s.as_str());
+    sqlx::query!(
+        "INSERT INTO matches (home_players_id, guest_players_id, result, timestamp, home_side, response_ms, outcome, annotator, session_id, excluded) VALUES (?, ?, ?, COALESCE(?, strftime('%Y-%m-%d %H:%M','now')), ?, ?, ?, ?, ?, ?)",
+        m.home_id,
+        m.guest_id,
+        result,
+        timestamp,
+        home_side,
+        m.response_ms,
+        outcome,
+        m.annotator,
+        m.session_id,
+        excluded
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn insert_flag(
+    tx: &mut SqliteConnection,
+    id: u32,
+    reason: &str,
+    comment: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO flags (players_id, reason, comment, timestamp) VALUES (?, ?, ?, strftime('%Y-%m-%d %H:%M','now'))",
+        id,
+        reason,
+        comment
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("UPDATE players SET flagged = TRUE WHERE id = ?", id)
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn review_player(
+    tx: &mut SqliteConnection,
+    id: u32,
+    flagged: bool,
+    status: &str,
+) -> Result<bool> {
+    let updated = sqlx::query!(
+        "UPDATE players SET flagged = ? WHERE id = ? AND flagged",
+        flagged,
+        id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if updated == 0 {
+        return Ok(false);
+    }
+    sqlx::query!(
+        "UPDATE flags SET status = ? WHERE players_id = ? AND status = 'open'",
+        status,
+        id
+    )
+    .execute(tx)
+    .await?;
+    Ok(true)
+}
+
+pub(super) async fn latest_session(
+    tx: &mut SqliteConnection,
+    annotator: &str,
+) -> Result<Option<(i64, i64, i64, i64)>> {
+    Ok(sqlx::query!(
+        "SELECT id, started, last_active, matches FROM sessions WHERE annotator = ? ORDER BY id DESC LIMIT 1",
+        annotator
+    )
+    .fetch_optional(tx)
+    .await?
+    .map(|s| (s.id, s.started, s.last_active, s.matches)))
+}
+
+pub(super) async fn continue_session(tx: &mut SqliteConnection, id: i64, now: i64) -> Result<()> {
+    sqlx::query!(
+        "UPDATE sessions SET last_active = ?, matches = matches + 1 WHERE id = ?",
+        now,
+        id
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn start_session(
+    tx: &mut SqliteConnection,
+    annotator: &str,
+    now: i64,
+) -> Result<i64> {
+    Ok(sqlx::query!(
+        "INSERT INTO sessions (annotator, started, last_active, matches) VALUES (?, ?, ?, 1)",
+        annotator,
+        now,
+        now
+    )
+    .execute(tx)
+    .await?
+    .last_insert_rowid())
+}
@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// A player as seen by the Swiss pairing
+pub struct Entrant {
+    pub id: i64,
+    pub rating: f64,
+    /// number of games already played
+    pub games: usize,
+}
+
+fn pair_key(a: i64, b: i64) -> (i64, i64) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+/// Collects all pairs which already met, independent of the home/guest order.
+pub fn played_pairs(matches: impl IntoIterator<Item = (i64, i64)>) -> HashSet<(i64, i64)> {
+    matches
+        .into_iter()
+        .map(|(home, guest)| pair_key(home, guest))
+        .collect()
+}
+
+/// Swiss-system pairing on the current ratings.
+///
+/// The players with the fewest games form the current round. Each of them
+/// gets paired with the closest rated player of the round it has not met yet.
+/// As the pairing is based on the glicko rating instead of the raw wins,
+/// a single contradicting comparison does not fix a player on a wrong position,
+/// it gets corrected in the next rounds by its neighbours.
+/// Leftover players of a round are paired with the closest rated players of the next round.
+///
+/// Returns at most `n_pairs` index pairs into `entrants`.
+pub fn pair(
+    entrants: &[Entrant],
+    played: &HashSet<(i64, i64)>,
+    n_pairs: usize,
+) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..entrants.len()).collect();
+    order.sort_by(|&a, &b| {
+        entrants[a].games.cmp(&entrants[b].games).then(
+            entrants[b]
+                .rating
+                .partial_cmp(&entrants[a].rating)
+                .unwrap_or(Ordering::Equal),
+        )
+    });
+
+    let mut paired = vec![false; entrants.len()];
+    let mut result = Vec::new();
+    for (pos, &home) in order.iter().enumerate() {
+        if result.len() >= n_pairs {
+            break;
+        }
+        if paired[home] {
+            continue;
+        }
+        let round = entrants[home].games;
+        let mut candidates: Vec<usize> = order[pos + 1..]
+            .iter()
+            .copied()
+            .filter(|&guest| !paired[guest])
+            .collect();
+        // closest rated player of the same round first, then the next rounds
+        candidates.sort_by(|&a, &b| {
+            let tier = |i: usize| entrants[i].games.saturating_sub(round);
+            let diff = |i: usize| (entrants[i].rating - entrants[home].rating).abs();
+            tier(a)
+                .cmp(&tier(b))
+                .then(diff(a).partial_cmp(&diff(b)).unwrap_or(Ordering::Equal))
+        });
+
+        let new_opponent = candidates
+            .iter()
+            .find(|&&guest| !played.contains(&pair_key(entrants[home].id, entrants[guest].id)));
+        if let Some(&guest) = new_opponent.or(candidates.first()) {
+            paired[home] = true;
+            paired[guest] = true;
+            result.push((home, guest));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entrants(ratings: &[(f64, usize)]) -> Vec<Entrant> {
+        ratings
+            .iter()
+            .enumerate()
+            .map(|(i, &(rating, games))| Entrant {
+                id: i as i64 + 1,
+                rating,
+                games,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pair_adjacent_ratings() {
+        let e = entrants(&[(1000.0, 0), (2000.0, 0), (1100.0, 0), (2100.0, 0)]);
+        let pairs = pair(&e, &HashSet::new(), 10);
+        assert_eq!(pairs.len(), 2);
+        assert!(pairs.contains(&(3, 1)));
+        assert!(pairs.contains(&(2, 0)));
+    }
+
+    #[test]
+    fn test_pair_avoids_rematch() {
+        let e = entrants(&[(1000.0, 1), (1010.0, 1), (1500.0, 1), (1510.0, 1)]);
+        let played = played_pairs([(1, 2), (3, 4)]);
+        let pairs = pair(&e, &played, 10);
+        assert_eq!(pairs.len(), 2);
+        for (a, b) in pairs {
+            assert!(!played.contains(&pair_key(e[a].id, e[b].id)));
+        }
+    }
+
+    #[test]
+    fn test_pair_prefers_players_with_fewer_games() {
+        let e = entrants(&[(1000.0, 3), (1000.0, 3), (1500.0, 0), (3000.0, 0)]);
+        let pairs = pair(&e, &HashSet::new(), 1);
+        assert_eq!(pairs, vec![(3, 2)]);
+    }
+
+    #[test]
+    fn test_pair_odd_round_uses_next_tier() {
+        let e = entrants(&[(1000.0, 0), (1200.0, 1), (3000.0, 1)]);
+        let pairs = pair(&e, &HashSet::new(), 10);
+        assert_eq!(pairs, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_pair_allows_rematch_if_nothing_else_left() {
+        let e = entrants(&[(1000.0, 1), (1010.0, 1)]);
+        let played = played_pairs([(2, 1)]);
+        assert_eq!(pair(&e, &played, 10), vec![(1, 0)]);
+    }
+}
@@ -6,10 +6,19 @@ use lightningcss::rules::CssRule;
 use lightningcss::selector::Component;
 use lightningcss::stylesheet::{ParserOptions, PrinterOptions, StyleSheet};
 
+/// html pages which get embedded into the server
+const PAGES: [&str; 3] = ["index.html", "admin.html", "leaderboard.html"];
+
 fn main() {
     let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
-    let html = std::fs::read_to_string(root.join("static/index.html"))
-        .expect("Failed to read static/index.html");
+    let pages: Vec<(&str, String)> = PAGES
+        .iter()
+        .map(|page| {
+            let html = std::fs::read_to_string(root.join("static").join(page))
+                .unwrap_or_else(|_| panic!("Failed to read static/{page}"));
+            (*page, html)
+        })
+        .collect();
     let css = std::fs::read_to_string(root.join("static/picnic.min.css"))
         .expect("Failed to read static/picnic.min.css");
 
@@ -17,21 +26,39 @@ fn main() {
     let is_release = std::env::var("PROFILE").unwrap() == "release";
 
     if is_release {
-        let used_classes = extract_used_classes(&html);
+        let mut used_classes = HashSet::new();
+        for (_, html) in &pages {
+            used_classes.extend(extract_used_classes(html));
+        }
         let filtered_css = tree_shake_css(&css, &used_classes);
 
         let mut cfg = minify_html::Cfg::new();
         cfg.minify_css = true;
         cfg.minify_js = true;
-        let minified_html = minify_html::minify(html.as_bytes(), &cfg);
-        let minified_html = strip_whitespace(&minified_html);
 
         let out = Path::new(&out);
-        std::fs::write(out.join("index.html"), &minified_html).expect("Failed to write index.html");
+        for (page, html) in &pages {
+            let minified_html = minify_html::minify(html.as_bytes(), &cfg);
+            let minified_html = strip_whitespace(&minified_html);
+
+            std::fs::write(out.join(page), &minified_html)
+                .unwrap_or_else(|_| panic!("Failed to write {page}"));
+            std::fs::write(
+                out.join(format!("{page}.etag")),
+                compute_etag(&minified_html),
+            )
+            .unwrap_or_else(|_| panic!("Failed to write {page}.etag"));
+
+            let h_orig = html.len();
+            let h_after = minified_html.len();
+            let h_saved = h_orig - h_after;
+            let h_pct = h_saved as f64 / h_orig as f64 * 100.0;
+            println!(
+                "cargo::warning=HTML minify {page}: {h_orig} B -> {h_after} B ({h_saved} B, {h_pct:.1}% saved)"
+            );
+        }
         std::fs::write(out.join("picnic.min.css"), filtered_css.as_bytes())
             .expect("Failed to write picnic.min.css");
-        std::fs::write(out.join("index.html.etag"), compute_etag(&minified_html))
-            .expect("Failed to write index.html.etag");
         std::fs::write(
             out.join("picnic.min.css.etag"),
             compute_etag(filtered_css.as_bytes()),
@@ -45,25 +72,21 @@ fn main() {
         println!(
             "cargo::warning=CSS tree-shake: {orig} B -> {after} B ({saved} B, {pct:.1}% saved)"
         );
-
-        let h_orig = html.len();
-        let h_after = minified_html.len();
-        let h_saved = h_orig - h_after;
-        let h_pct = h_saved as f64 / h_orig as f64 * 100.0;
-        println!(
-            "cargo::warning=HTML minify: {h_orig} B -> {h_after} B ({h_saved} B, {h_pct:.1}% saved)"
-        );
     } else {
-        std::fs::write(Path::new(&out).join("index.html"), html.as_bytes())
-            .expect("Failed to write index.html");
+        for (page, html) in &pages {
+            std::fs::write(Path::new(&out).join(page), html.as_bytes())
+                .unwrap_or_else(|_| panic!("Failed to write {page}"));
+        }
         std::fs::write(Path::new(&out).join("picnic.min.css"), css.as_bytes())
             .expect("Failed to write picnic.min.css");
     }
 
-    println!(
-        "cargo::rerun-if-changed={}",
-        root.join("static/index.html").display()
-    );
+    for page in PAGES {
+        println!(
+            "cargo::rerun-if-changed={}",
+            root.join("static").join(page).display()
+        );
+    }
     println!(
         "cargo::rerun-if-changed={}",
         root.join("static/picnic.min.css").display()
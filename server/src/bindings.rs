@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// Keyboard shortcuts of the annotation page.
+/// Keys are `KeyboardEvent.key` values like "x" or "ArrowLeft".
+/// Actions missing in a configuration file keep their default keys.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    /// the image shown on the left is better
+    pub left: Vec<String>,
+    /// the image shown on the right is better
+    pub right: Vec<String>,
+    /// the image shown on the left is slightly better, only with graded outcomes
+    pub left_slightly: Vec<String>,
+    /// the image shown on the right is slightly better, only with graded outcomes
+    pub right_slightly: Vec<String>,
+    /// both images are equal
+    pub draw: Vec<String>,
+    /// the pair can not be judged, the ratings stay unchanged
+    pub skip: Vec<String>,
+    /// the image shown on the left is broken and needs a review
+    pub flag_left: Vec<String>,
+    /// the image shown on the right is broken and needs a review
+    pub flag_right: Vec<String>,
+    /// swaps the displayed sides of the images
+    pub swap: Vec<String>,
+    pub fullscreen: Vec<String>,
+    pub zoom_in: Vec<String>,
+    pub zoom_out: Vec<String>,
+    pub reset_zoom: Vec<String>,
+}
+
+fn keys(keys: &[&str]) -> Vec<String> {
+    keys.iter().map(|k| k.to_string()).collect()
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            left: keys(&["x", "ArrowLeft"]),
+            right: keys(&["m", "ArrowRight"]),
+            left_slightly: keys(&["c"]),
+            right_slightly: keys(&["n"]),
+            draw: keys(&["b", "ArrowDown"]),
+            skip: keys(&["u"]),
+            flag_left: Vec::new(),
+            flag_right: Vec::new(),
+            swap: keys(&["s"]),
+            fullscreen: keys(&["f"]),
+            zoom_in: keys(&["+", "="]),
+            zoom_out: keys(&["-"]),
+            reset_zoom: keys(&["0"]),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// reads the key bindings from a json file
+    pub fn from_file(path: &Path) -> Result<KeyBindings> {
+        let bindings: KeyBindings = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        bindings.validate()?;
+        Ok(bindings)
+    }
+
+    fn actions(&self) -> [(&'static str, &Vec<String>); 13] {
+        [
+            ("left", &self.left),
+            ("right", &self.right),
+            ("left_slightly", &self.left_slightly),
+            ("right_slightly", &self.right_slightly),
+            ("draw", &self.draw),
+            ("skip", &self.skip),
+            ("flag_left", &self.flag_left),
+            ("flag_right", &self.flag_right),
+            ("swap", &self.swap),
+            ("fullscreen", &self.fullscreen),
+            ("zoom_in", &self.zoom_in),
+            ("zoom_out", &self.zoom_out),
+            ("reset_zoom", &self.reset_zoom),
+        ]
+    }
+
+    /// every key may trigger only one action
+    pub fn validate(&self) -> Result<()> {
+        let mut used: HashMap<String, &str> = HashMap::new();
+        for (action, keys) in self.actions() {
+            for key in keys {
+                if key.is_empty() {
+                    bail!("empty key for action \"{action}\"");
+                }
+                // the page matches single characters case-insensitively
+                let normalized = if key.chars().count() == 1 {
+                    key.to_lowercase()
+                } else {
+                    key.clone()
+                };
+                if let Some(other) = used.insert(normalized, action) {
+                    bail!("key \"{key}\" is bound to \"{other}\" and \"{action}\"");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_bindings_are_valid() {
+        assert!(KeyBindings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_partial_configuration_keeps_defaults() {
+        let bindings: KeyBindings = serde_json::from_str(r#"{"left": ["a"]}"#).unwrap();
+        assert_eq!(bindings.left, vec!["a"]);
+        assert_eq!(bindings.right, KeyBindings::default().right);
+        assert!(serde_json::from_str::<KeyBindings>(r#"{"lefty": ["a"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_keys_are_rejected() {
+        let bindings = KeyBindings {
+            swap: vec!["x".to_string()],
+            ..KeyBindings::default()
+        };
+        assert!(bindings.validate().is_err());
+
+        let bindings = KeyBindings {
+            swap: vec!["X".to_string()],
+            ..KeyBindings::default()
+        };
+        assert!(bindings.validate().is_err());
+    }
+}
@@ -0,0 +1,467 @@
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use actix_web::{HttpRequest, HttpResponse, error, get, web};
+use anyhow::Result;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use serde::Deserialize;
+
+use crate::preview::PreviewSettings;
+
+/// requested widths get rounded up to a multiple of this value,
+/// so the number of cached variants per image stays small
+const WIDTH_STEP: u32 = 128;
+const MAX_WIDTH: u32 = 4096;
+const JPEG_QUALITY: u8 = 85;
+const CACHE_CONTROL: &str = "public, max-age=0, must-revalidate";
+
+/// numbers the temporary files of the cache, so concurrent writes never share one
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Serves the images of the image directory.
+/// Resized variants and previews of scientific images get cached on disk.
+pub struct ImageService {
+    image_dir: PathBuf,
+    cache_dir: PathBuf,
+    preview: PreviewSettings,
+}
+
+impl ImageService {
+    /// The cache gets pruned to `cache_max_bytes`, it is not limited while the server runs.
+    pub fn new(
+        image_dir: &str,
+        cache_dir: &str,
+        cache_max_bytes: u64,
+        preview: PreviewSettings,
+    ) -> Result<ImageService> {
+        std::fs::create_dir_all(cache_dir)?;
+        let removed = prune_cache(Path::new(cache_dir), cache_max_bytes)?;
+        if removed > 0 {
+            tracing::info!(
+                "Removed {} cached images to stay below the cache size.",
+                removed
+            );
+        }
+        Ok(ImageService {
+            image_dir: PathBuf::from(image_dir),
+            cache_dir: PathBuf::from(cache_dir),
+            preview,
+        })
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Default, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// small, but without transparency
+    #[default]
+    Jpeg,
+    /// lossless, keeps transparency
+    Webp,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Jpeg => "jpg",
+            Format::Webp => "webp",
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            Format::Jpeg => "image/jpeg",
+            Format::Webp => "image/webp",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImageQuery {
+    /// width of the resized image. The original, or the full size preview
+    /// of images browsers can not display, is served if not given.
+    w: Option<u32>,
+    #[serde(default)]
+    format: Format,
+}
+
+/// only plain relative paths inside of the image directory are allowed
+fn is_safe_path(name: &str) -> bool {
+    !name.is_empty()
+        && Path::new(name)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn variant_width(requested: u32) -> u32 {
+    requested
+        .div_ceil(WIDTH_STEP)
+        .clamp(1, MAX_WIDTH / WIDTH_STEP)
+        * WIDTH_STEP
+}
+
+/// the etag changes, whenever the original image or the preview settings change
+fn variant_etag(
+    name: &str,
+    meta: &std::fs::Metadata,
+    width: u32,
+    format: Format,
+    preview: &PreviewSettings,
+) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    name.hash(&mut hasher);
+    meta.len().hash(&mut hasher);
+    meta.modified().ok().hash(&mut hasher);
+    width.hash(&mut hasher);
+    format.hash(&mut hasher);
+    preview.fingerprint().hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// a width of 0 keeps the original size
+fn resize(
+    original: &Path,
+    width: u32,
+    format: Format,
+    preview: &PreviewSettings,
+) -> Result<Vec<u8>> {
+    let img = if preview.handles(original) {
+        preview.render(original)?
+    } else {
+        image::ImageReader::open(original)?
+            .with_guessed_format()?
+            .decode()?
+    };
+    // never upscale
+    let img = if width > 0 && img.width() > width {
+        img.resize(width, u32::MAX, FilterType::Triangle)
+    } else {
+        img
+    };
+    let mut buf = Vec::new();
+    match format {
+        Format::Jpeg => img
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, JPEG_QUALITY))?,
+        Format::Webp => img
+            .to_rgba8()
+            .write_with_encoder(WebPEncoder::new_lossless(&mut buf))?,
+    }
+    Ok(buf)
+}
+
+/// returns the cached variant or creates it
+fn load_variant(
+    original: &Path,
+    cached: &Path,
+    width: u32,
+    format: Format,
+    preview: &PreviewSettings,
+) -> Result<Vec<u8>> {
+    if let Ok(bytes) = std::fs::read(cached) {
+        return Ok(bytes);
+    }
+    let bytes = resize(original, width, format, preview)?;
+    // write to a temporary file first, so concurrent requests never read a partial file
+    let tmp = tmp_path(cached);
+    std::fs::write(&tmp, &bytes).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })?;
+    std::fs::rename(&tmp, cached)?;
+    Ok(bytes)
+}
+
+/// deletes the oldest variants until the cache is not larger than `max_bytes`,
+/// and the temporary files of interrupted writes. Returns the number of deleted variants.
+fn prune_cache(cache_dir: &Path, max_bytes: u64) -> Result<usize> {
+    let mut variants = Vec::new();
+    for entry in std::fs::read_dir(cache_dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if !meta.is_file() {
+            continue;
+        }
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "tmp") {
+            std::fs::remove_file(&path)?;
+            continue;
+        }
+        variants.push((meta.modified()?, meta.len(), path));
+    }
+    let mut size: u64 = variants.iter().map(|(_, len, _)| len).sum();
+    variants.sort();
+    let mut removed = 0;
+    for (_, len, path) in variants {
+        if size <= max_bytes {
+            break;
+        }
+        std::fs::remove_file(&path)?;
+        size -= len;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// a temporary file next to `cached`, unique for every call
+fn tmp_path(cached: &Path) -> PathBuf {
+    let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    cached.with_extension(format!("{}-{n}.tmp", std::process::id()))
+}
+
+#[get("/images/{name:.*}")]
+pub async fn serve_image(
+    req: HttpRequest,
+    name: web::Path<String>,
+    query: web::Query<ImageQuery>,
+    service: web::Data<ImageService>,
+) -> actix_web::Result<HttpResponse> {
+    let name = name.into_inner();
+    if !is_safe_path(&name) {
+        return Err(error::ErrorBadRequest("invalid image path"));
+    }
+    let original = service.image_dir.join(&name);
+
+    let width = match query.w {
+        Some(width) => variant_width(width),
+        // browsers can not display these, so a full size preview is served instead
+        None if service.preview.handles(&original) => 0,
+        None => {
+            let file = actix_files::NamedFile::open_async(&original).await?;
+            return Ok(file.into_response(&req));
+        }
+    };
+
+    let meta = web::block({
+        let original = original.clone();
+        move || std::fs::metadata(original)
+    })
+    .await?
+    .map_err(|_| error::ErrorNotFound("image not found"))?;
+
+    let format = query.format;
+    let etag = variant_etag(&name, &meta, width, format, &service.preview);
+    if let Some(if_none_match) = req.headers().get("if-none-match")
+        && let Ok(val) = if_none_match.to_str()
+        && val == etag
+    {
+        return Ok(HttpResponse::NotModified().finish());
+    }
+
+    let cached =
+        service
+            .cache_dir
+            .join(format!("{}.{}", etag.trim_matches('"'), format.extension()));
+    let preview = service.preview.clone();
+    let bytes = web::block(move || load_variant(&original, &cached, width, format, &preview))
+        .await?
+        .map_err(|err| error::ErrorInternalServerError(err.to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(("ETag", etag))
+        .insert_header(("Cache-Control", CACHE_CONTROL))
+        .body(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, test as actix_test};
+
+    fn test_dirs(name: &str) -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("regan-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let images = root.join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        image::RgbaImage::from_pixel(300, 150, image::Rgba([200, 10, 10, 255]))
+            .save(images.join("a.png"))
+            .unwrap();
+        (images, root.join("cache"))
+    }
+
+    #[test]
+    fn test_tmp_path_is_unique() {
+        let cached = Path::new("cache/a.png.w256.webp");
+        let first = tmp_path(cached);
+        let second = tmp_path(cached);
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), cached.parent());
+        assert!(first.to_string_lossy().ends_with(".tmp"));
+    }
+
+    #[test]
+    fn test_prune_cache_deletes_the_oldest_variants() {
+        let (images, cache) = test_dirs("prune");
+        std::fs::create_dir_all(&cache).unwrap();
+        let now = std::time::SystemTime::now();
+        for (i, name) in ["old.jpg", "middle.jpg", "new.jpg"].into_iter().enumerate() {
+            let file = std::fs::File::create(cache.join(name)).unwrap();
+            file.set_len(100).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(60 * (3 - i as u64)))
+                .unwrap();
+        }
+        std::fs::write(cache.join("new.1-0.tmp"), [0; 10]).unwrap();
+
+        assert_eq!(prune_cache(&cache, 300).unwrap(), 0);
+        assert!(!cache.join("new.1-0.tmp").exists());
+        assert_eq!(prune_cache(&cache, 250).unwrap(), 1);
+        assert!(!cache.join("old.jpg").exists());
+        assert!(cache.join("middle.jpg").exists());
+        assert_eq!(prune_cache(&cache, 0).unwrap(), 2);
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir_all(images.parent().unwrap());
+    }
+
+    #[test]
+    fn test_is_safe_path() {
+        assert!(is_safe_path("a.png"));
+        assert!(is_safe_path("sub/a.png"));
+        assert!(!is_safe_path(""));
+        assert!(!is_safe_path("../a.png"));
+        assert!(!is_safe_path("/etc/passwd"));
+        assert!(!is_safe_path("sub/../../a.png"));
+    }
+
+    #[test]
+    fn test_variant_width() {
+        assert_eq!(variant_width(0), 128);
+        assert_eq!(variant_width(1), 128);
+        assert_eq!(variant_width(128), 128);
+        assert_eq!(variant_width(129), 256);
+        assert_eq!(variant_width(100_000), MAX_WIDTH);
+    }
+
+    #[actix_web::test]
+    async fn test_resized_image_is_cached_with_etag() {
+        let (images, cache) = test_dirs("resize");
+        let service = ImageService::new(
+            images.to_str().unwrap(),
+            cache.to_str().unwrap(),
+            u64::MAX,
+            PreviewSettings::default(),
+        )
+        .unwrap();
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(service))
+                .service(serve_image),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::get()
+            .uri("/images/a.png?w=100")
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/jpeg");
+        let etag = resp.headers().get("etag").unwrap().clone();
+        let body = actix_test::read_body(resp).await;
+        let resized = image::load_from_memory(&body).unwrap();
+        assert_eq!((resized.width(), resized.height()), (128, 64));
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 1);
+
+        let req = actix_test::TestRequest::get()
+            .uri("/images/a.png?w=100")
+            .insert_header(("if-none-match", etag))
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 304);
+
+        // no upscaling
+        let req = actix_test::TestRequest::get()
+            .uri("/images/a.png?w=1000&format=webp")
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/webp");
+        let body = actix_test::read_body(resp).await;
+        let resized = image::load_from_memory(&body).unwrap();
+        assert_eq!(resized.width(), 300);
+
+        let _ = std::fs::remove_dir_all(images.parent().unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_original_and_invalid_paths() {
+        let (images, cache) = test_dirs("original");
+        let service = ImageService::new(
+            images.to_str().unwrap(),
+            cache.to_str().unwrap(),
+            u64::MAX,
+            PreviewSettings::default(),
+        )
+        .unwrap();
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(service))
+                .service(serve_image),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::get()
+            .uri("/images/a.png")
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let body = actix_test::read_body(resp).await;
+        assert_eq!(body, std::fs::read(images.join("a.png")).unwrap());
+
+        let req = actix_test::TestRequest::get()
+            .uri("/images/missing.png?w=100")
+            .to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), 404);
+
+        let req = actix_test::TestRequest::get()
+            .uri("/images/..%2Fa.png?w=100")
+            .to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), 400);
+
+        let _ = std::fs::remove_dir_all(images.parent().unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_tiff_is_served_as_preview() {
+        let (images, cache) = test_dirs("preview");
+        let data: Vec<u16> = (0..300 * 150).map(|i| (i % 4096) as u16).collect();
+        let file = std::fs::File::create(images.join("b.tif")).unwrap();
+        tiff::encoder::TiffEncoder::new(file)
+            .unwrap()
+            .write_image::<tiff::encoder::colortype::Gray16>(300, 150, &data)
+            .unwrap();
+        let service = ImageService::new(
+            images.to_str().unwrap(),
+            cache.to_str().unwrap(),
+            u64::MAX,
+            PreviewSettings::default(),
+        )
+        .unwrap();
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(service))
+                .service(serve_image),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::get()
+            .uri("/images/b.tif")
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/jpeg");
+        let body = actix_test::read_body(resp).await;
+        let preview = image::load_from_memory(&body).unwrap();
+        assert_eq!((preview.width(), preview.height()), (300, 150));
+
+        let req = actix_test::TestRequest::get()
+            .uri("/images/b.tif?w=100")
+            .to_request();
+        let body = actix_test::call_and_read_body(&app, req).await;
+        let preview = image::load_from_memory(&body).unwrap();
+        assert_eq!(preview.width(), 128);
+
+        let _ = std::fs::remove_dir_all(images.parent().unwrap());
+    }
+}
@@ -1,17 +1,26 @@
-#[cfg(not(debug_assertions))]
-use actix_web::HttpRequest;
-use actix_web::{App, HttpResponse, HttpServer, Responder, error, get, post, web};
+mod bindings;
+mod images;
+mod preview;
+
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, Responder, error, get, post, web};
 use anyhow::Result;
+use bindings::KeyBindings;
 use clap::Parser;
-use image_collection::{ImageCollection, ImageCollectionOptions, Match};
+use image_collection::{
+    BackupOptions, CampaignComplete, FlagReason, FlagStatus, ImageCollection,
+    ImageCollectionOptions, LowAccuracy, Match, MatchRecord, Matchmaking, PlayerOrder, PlayerPage,
+    PlayerQuery, QualityControl, ResponseTimeWeighting, SessionLimits, StoppingCriterion,
+};
+use preview::{PreviewSettings, RawFormat, Window};
+use serde::{Deserialize, Serialize};
 
 const TRANSPARENT_SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#;
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 struct Args {
-    /// The output database. Must be a file as only sqlite is currently supported
-    /// Write the path in the form of "sqlite://<rel-path>"
+    /// The output database. Either a sqlite file in the form of "sqlite://<rel-path>"
+    /// or a PostgreSQL server in the form of "postgres://<user>@<host>/<database>"
     #[clap(short, long, default_value = "sqlite://out.db")]
     output: String,
 
@@ -28,6 +37,154 @@ struct Args {
     /// directory of the image
     #[clap(long, default_value = "./images")]
     image_dir: String,
+
+    /// directory for the resized images.
+    /// Resized images are requested via "/images/<name>?w=<width>"
+    #[clap(long, default_value = "./image_cache")]
+    cache_dir: String,
+
+    /// Upper limit of the image cache in MiB.
+    /// The oldest resized images get deleted on start, if the cache is larger
+    #[clap(long, default_value_t = 1024)]
+    cache_max_mb: u64,
+
+    /// The matchmaking strategy.
+    /// "random" selects the most uncertain images and a random opponent in their confidence interval.
+    /// "swiss" plays Swiss-system rounds, so every image gets the same number of comparisons.
+    #[clap(long, default_value_t = Matchmaking::Random)]
+    matchmaking: Matchmaking,
+
+    /// Stop serving duels when the highest rating deviation of all images is below this value
+    #[clap(long)]
+    target_deviation: Option<f64>,
+
+    /// Stop serving duels when the estimated root mean squared rank error is below this value
+    #[clap(long)]
+    target_rank_error: Option<f64>,
+
+    /// Stop serving duels after this number of games
+    #[clap(long)]
+    max_games: Option<u64>,
+
+    /// Estimate the preference of the annotators for the left side
+    /// and for the side which won the previous duel, and remove it from the rating updates
+    #[clap(long)]
+    correct_position_bias: bool,
+
+    /// Use the response times of the annotators as confidence, in the form of "<fast_ms>,<slow_ms>[,<min_confidence>]".
+    /// Decisions up to fast_ms count fully, slower decisions are treated more and more as draws,
+    /// down to min_confidence (default 0.5) of the result from slow_ms on. E.g. "2000,15000,0.5"
+    #[clap(long)]
+    response_time_weighting: Option<ResponseTimeWeighting>,
+
+    /// Windowing of scientific images (16-bit or float TIFFs, stacks and raw files) for their previews.
+    /// Either percentiles for a contrast stretch per channel like "0.5%,99.5%"
+    /// or fixed sample values like "100,4000"
+    #[clap(long, default_value = "0.5%,99.5%")]
+    preview_window: Window,
+
+    /// Channels of scientific images shown in the preview, one for grayscale or three for RGB, e.g. "2,1,0".
+    /// Pages of a multi-page TIFF count as channels.
+    /// Defaults to the first channel or the first three channels
+    #[clap(long, value_delimiter = ',')]
+    preview_channels: Vec<usize>,
+
+    /// Layout of headerless ".raw" images in the form of "<width>x<height>x<channels>:<u8|u16le|u16be|f32le>",
+    /// e.g. "2048x1536x1:u16le". Raw images can only be shown if this is set
+    #[clap(long)]
+    raw_format: Option<RawFormat>,
+
+    /// JSON file with the keyboard shortcuts of the annotation page,
+    /// e.g. {"left": ["a", "ArrowLeft"], "right": ["l"]}. Missing actions keep their default keys
+    #[clap(long)]
+    key_bindings: Option<std::path::PathBuf>,
+
+    /// Offer "slightly better" on the annotation page in addition to better and equal.
+    /// A slight win scores 0.75 instead of 1
+    #[clap(long)]
+    graded_outcomes: bool,
+
+    /// CSV file with pairs of images with a known result, in the form of "home,guest,result".
+    /// They are interleaved with the regular duels to check the annotators
+    #[clap(long)]
+    gold_pairs: Option<std::path::PathBuf>,
+
+    /// Share of the served duels which are gold pairs
+    #[clap(long, default_value_t = 0.05)]
+    gold_rate: f64,
+
+    /// Annotators with a lower share of correct answers on the gold pairs are down-weighted or excluded.
+    /// Must be above 0.5, which is chance level
+    #[clap(long)]
+    min_annotator_accuracy: Option<f64>,
+
+    /// Number of gold answers needed before the accuracy of an annotator is judged
+    #[clap(long, default_value_t = 10)]
+    min_gold_answers: u64,
+
+    /// What happens with annotators below the minimal accuracy.
+    /// "down-weight" counts their results as a fraction of a game, "exclude" ignores them in the ratings
+    #[clap(long, default_value_t = LowAccuracy::DownWeight)]
+    low_accuracy: LowAccuracy,
+
+    /// Share of the served duels which check the consistency of the annotators,
+    /// by repeating a judged pair or by asking for the missing pair of a triad
+    #[clap(long, default_value_t = 0.0)]
+    consistency_rate: f64,
+
+    /// Number of judgements after which an annotator has to take a break
+    #[clap(long)]
+    session_max_matches: Option<u64>,
+
+    /// Minutes after which an annotator has to take a break
+    #[clap(long)]
+    session_max_minutes: Option<u64>,
+
+    /// Length of the break in minutes. A pause of this length also starts a new session
+    #[clap(long, default_value_t = 15)]
+    session_break_minutes: u64,
+
+    /// Opens the sqlite database without an exclusive lock and serves exports and statistics
+    /// from this many read-only connections, so other tools can read the database while running
+    #[clap(long)]
+    read_connections: Option<u32>,
+
+    /// Directory for snapshots of the sqlite database, written by "POST /admin/backup"
+    #[clap(long)]
+    backup_dir: Option<String>,
+
+    /// Number of snapshots kept in the backup directory, older ones get deleted
+    #[clap(long, default_value_t = 10)]
+    backup_keep: usize,
+
+    /// Writes a snapshot every this many minutes. Needs --backup-dir
+    #[clap(long)]
+    backup_interval_minutes: Option<u64>,
+
+    /// Token for the admin actions: setting game weights, reviewing flagged images and backups.
+    /// They need the header "Authorization: Bearer <token>".
+    /// Without a token, everyone who can reach the server can use them, so only omit it in a trusted network
+    #[clap(long)]
+    admin_token: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Imports judgements from a csv file with the header "home,guest,result[,timestamp,annotator]"
+    /// into the output database and exits. The ratings are updated in the order of the file
+    Import {
+        /// csv file of the judgements, the images are given by their file names
+        file: std::path::PathBuf,
+    },
+}
+
+/// Options of the annotation page
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+struct PageSettings {
+    graded_outcomes: bool,
 }
 
 #[cfg(not(debug_assertions))]
@@ -39,6 +196,18 @@ static INDEX_HTML: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/index.html"
 #[cfg(not(debug_assertions))]
 static INDEX_ETAG: &str = include_str!(concat!(env!("OUT_DIR"), "/index.html.etag"));
 
+#[cfg(not(debug_assertions))]
+static ADMIN_HTML: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/admin.html"));
+
+#[cfg(not(debug_assertions))]
+static ADMIN_ETAG: &str = include_str!(concat!(env!("OUT_DIR"), "/admin.html.etag"));
+
+#[cfg(not(debug_assertions))]
+static LEADERBOARD_HTML: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/leaderboard.html"));
+
+#[cfg(not(debug_assertions))]
+static LEADERBOARD_ETAG: &str = include_str!(concat!(env!("OUT_DIR"), "/leaderboard.html.etag"));
+
 #[cfg(not(debug_assertions))]
 static STYLE_CSS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/picnic.min.css"));
 
@@ -77,6 +246,35 @@ async fn index() -> impl Responder {
     actix_files::NamedFile::open("static/index.html")
 }
 
+#[get("/admin")]
+#[cfg(not(debug_assertions))]
+async fn admin(req: HttpRequest) -> impl Responder {
+    cached_response(&req, ADMIN_ETAG, ADMIN_HTML, "text/html; charset=utf-8")
+}
+
+#[get("/admin")]
+#[cfg(debug_assertions)]
+async fn admin() -> impl Responder {
+    actix_files::NamedFile::open("static/admin.html")
+}
+
+#[get("/leaderboard")]
+#[cfg(not(debug_assertions))]
+async fn leaderboard(req: HttpRequest) -> impl Responder {
+    cached_response(
+        &req,
+        LEADERBOARD_ETAG,
+        LEADERBOARD_HTML,
+        "text/html; charset=utf-8",
+    )
+}
+
+#[get("/leaderboard")]
+#[cfg(debug_assertions)]
+async fn leaderboard() -> impl Responder {
+    actix_files::NamedFile::open("static/leaderboard.html")
+}
+
 #[get("/style.css")]
 #[cfg(not(debug_assertions))]
 async fn style(req: HttpRequest) -> impl Responder {
@@ -96,25 +294,444 @@ async fn blank_image() -> HttpResponse {
         .body(TRANSPARENT_SVG)
 }
 
+/// upper limit of duels per request
+const MAX_PREFETCH: usize = 10;
+
+/// responds with the next duel or with the campaign status, when the campaign is complete.
+/// With `count`, a list of the next duels is returned, so the client can preload their images.
+/// The consistency checks of the `annotator` are taken from its own judgements.
+async fn next_duel_response(
+    collection: &ImageCollection,
+    count: Option<usize>,
+    annotator: Option<&str>,
+) -> actix_web::Result<HttpResponse> {
+    if let Some(count) = count
+        && !(1..=MAX_PREFETCH).contains(&count)
+    {
+        return Err(error::ErrorBadRequest(format!(
+            "count must be between 1 and {MAX_PREFETCH}"
+        )));
+    }
+    match collection.new_duels(count.unwrap_or(1), annotator).await {
+        Ok(duels) if count.is_some() => Ok(HttpResponse::Ok().json(duels)),
+        Ok(duels) => Ok(HttpResponse::Ok().json(&duels[0])),
+        Err(err) if err.is::<CampaignComplete>() => match collection.status().await {
+            Ok(status) => Ok(HttpResponse::Gone().json(status)),
+            Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+        },
+        Err(err) => Err(error::ErrorBadRequest(err.to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+struct MatchesQuery {
+    /// number of duels to return
+    count: Option<usize>,
+    /// annotator who judges the duels
+    annotator: Option<String>,
+}
+
 #[get("/matches")]
 async fn return_new_match(
+    query: web::Query<MatchesQuery>,
     collection: web::Data<ImageCollection>,
 ) -> actix_web::Result<HttpResponse> {
-    match collection.new_duel().await {
-        Ok(new_duel) => Ok(HttpResponse::Ok().json(new_duel)),
-        Err(err) => Err(error::ErrorBadRequest(err.to_string())),
-    }
+    next_duel_response(&collection, query.count, query.annotator.as_deref()).await
 }
 
 #[post("/matches")]
 async fn on_new_score(
     m: actix_web::web::Json<Match>,
+    query: web::Query<MatchesQuery>,
     collection: web::Data<ImageCollection>,
 ) -> actix_web::Result<HttpResponse> {
-    collection.insert_match(m.to_owned()).await;
-    match collection.new_duel().await {
-        Ok(new_duel) => Ok(HttpResponse::Ok().json(new_duel)),
-        Err(err) => Err(error::ErrorBadRequest(err.to_string())),
+    // the break is checked when the match joins the session, a dropped match is answered with 429
+    if let Some(session) = collection.insert_match(m.to_owned()).await {
+        return Ok(HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", session.break_seconds.to_string()))
+            .json(session));
+    }
+    let annotator = query.annotator.as_deref().or(m.annotator.as_deref());
+    next_duel_response(&collection, query.count, annotator).await
+}
+
+#[derive(Deserialize)]
+struct SessionQuery {
+    annotator: String,
+}
+
+#[get("/api/session")]
+async fn annotator_session(
+    query: web::Query<SessionQuery>,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    match collection.session(&query.annotator).await {
+        Ok(session) => Ok(HttpResponse::Ok().json(session)),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+#[get("/api/keybindings")]
+async fn serve_key_bindings(bindings: web::Data<KeyBindings>) -> HttpResponse {
+    HttpResponse::Ok().json(bindings.get_ref())
+}
+
+#[get("/api/settings")]
+async fn page_settings(settings: web::Data<PageSettings>) -> HttpResponse {
+    HttpResponse::Ok().json(settings.get_ref())
+}
+
+#[get("/status")]
+async fn campaign_status(
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    match collection.status().await {
+        Ok(status) => Ok(HttpResponse::Ok().json(status)),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+struct StatsQuery {
+    /// images with less games are counted
+    #[serde(default = "default_min_games")]
+    min_games: u64,
+    /// precision for the estimate of the remaining games
+    target_deviation: Option<f64>,
+}
+
+fn default_min_games() -> u64 {
+    5
+}
+
+#[get("/stats")]
+async fn campaign_stats(
+    query: web::Query<StatsQuery>,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    if query
+        .target_deviation
+        .is_some_and(|d| !d.is_finite() || d <= 0_f64)
+    {
+        return Err(error::ErrorBadRequest(
+            "target_deviation must be a positive number",
+        ));
+    }
+    match collection
+        .stats(query.min_games, query.target_deviation)
+        .await
+    {
+        Ok(stats) => Ok(HttpResponse::Ok().json(stats)),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+const MAX_PER_PAGE: u32 = 500;
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Deserialize)]
+struct PlayersQuery {
+    /// 1-based page number
+    #[serde(default = "default_page")]
+    page: u32,
+    #[serde(default = "default_per_page")]
+    per_page: u32,
+    #[serde(default)]
+    sort: PlayerOrder,
+    order: Option<SortOrder>,
+}
+
+fn default_page() -> u32 {
+    1
+}
+
+fn default_per_page() -> u32 {
+    50
+}
+
+#[derive(Serialize)]
+struct PlayersResponse {
+    page: u32,
+    per_page: u32,
+    #[serde(flatten)]
+    players: PlayerPage,
+}
+
+#[get("/api/players")]
+async fn list_players(
+    query: web::Query<PlayersQuery>,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    if query.page == 0 || query.per_page == 0 || query.per_page > MAX_PER_PAGE {
+        return Err(error::ErrorBadRequest(format!(
+            "page must be at least 1 and per_page between 1 and {MAX_PER_PAGE}"
+        )));
+    }
+    // highest rating, deviation or number of games first, if not stated otherwise
+    let descending = query.order.unwrap_or(SortOrder::Desc) == SortOrder::Desc;
+    let player_query = PlayerQuery {
+        order_by: query.sort,
+        descending,
+        offset: (query.page - 1).saturating_mul(query.per_page),
+        limit: query.per_page,
+    };
+    match collection.players(&player_query).await {
+        Ok(players) => Ok(HttpResponse::Ok().json(PlayersResponse {
+            page: query.page,
+            per_page: query.per_page,
+            players,
+        })),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+struct RecentMatchesQuery {
+    #[serde(default = "default_recent_matches")]
+    limit: u32,
+}
+
+fn default_recent_matches() -> u32 {
+    20
+}
+
+#[get("/api/matches")]
+async fn recent_matches(
+    query: web::Query<RecentMatchesQuery>,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    match collection.recent_matches(query.limit).await {
+        Ok(matches) => Ok(HttpResponse::Ok().json(matches)),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+/// all matches as csv, oldest first
+#[get("/api/export/matches.csv")]
+async fn export_matches(collection: web::Data<ImageCollection>) -> actix_web::Result<HttpResponse> {
+    let mut matches = collection
+        .recent_matches(u32::MAX)
+        .await
+        .map_err(|err| error::ErrorInternalServerError(err.to_string()))?;
+    matches.reverse();
+    let csv =
+        matches_csv(&matches).map_err(|err| error::ErrorInternalServerError(err.to_string()))?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            "Content-Disposition",
+            "attachment; filename=\"matches.csv\"",
+        ))
+        .body(csv))
+}
+
+fn matches_csv(matches: &[MatchRecord]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "id",
+        "home",
+        "guest",
+        "result",
+        "timestamp",
+        "home_side",
+        "response_ms",
+        "annotator",
+        "session_id",
+    ])?;
+    for m in matches {
+        writer.write_record([
+            m.id.to_string(),
+            m.home.clone(),
+            m.guest.clone(),
+            m.won.to_string(),
+            m.timestamp.clone(),
+            m.home_side.map(|s| s.to_string()).unwrap_or_default(),
+            m.response_ms.map(|ms| ms.to_string()).unwrap_or_default(),
+            m.annotator.clone().unwrap_or_default(),
+            m.session_id.map(|id| id.to_string()).unwrap_or_default(),
+        ])?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+#[derive(Deserialize)]
+struct ActivityQuery {
+    #[serde(default = "default_activity_hours")]
+    hours: u32,
+}
+
+fn default_activity_hours() -> u32 {
+    24
+}
+
+#[get("/api/annotators")]
+async fn list_annotators(
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    match collection.annotators().await {
+        Ok(annotators) => Ok(HttpResponse::Ok().json(annotators)),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+/// Token of the admin actions, they are open to everyone without it
+struct AdminToken(Option<String>);
+
+/// fails with 401, if a token is configured and the request does not carry it
+fn authorize(req: &HttpRequest) -> actix_web::Result<()> {
+    let Some(AdminToken(Some(token))) =
+        req.app_data::<web::Data<AdminToken>>().map(|t| t.get_ref())
+    else {
+        return Ok(());
+    };
+    let given = req
+        .headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    if given != Some(token.as_str()) {
+        return Err(error::ErrorUnauthorized("admin token is missing or wrong"));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct WeightRequest {
+    weight: f64,
+}
+
+/// sets the weight of the games of an annotator in the rating updates
+#[post("/api/annotators/{annotator}/weight")]
+async fn set_annotator_weight(
+    req: HttpRequest,
+    annotator: web::Path<String>,
+    request: web::Json<WeightRequest>,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    authorize(&req)?;
+    if !request.weight.is_finite() || request.weight < 0_f64 {
+        return Err(error::ErrorBadRequest(
+            "weight must be a non-negative number",
+        ));
+    }
+    match collection
+        .set_annotator_weight(&annotator, request.weight)
+        .await
+    {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+#[get("/api/activity")]
+async fn recent_activity(
+    query: web::Query<ActivityQuery>,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    match collection.activity(query.hours).await {
+        Ok(activity) => Ok(HttpResponse::Ok().json(activity)),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+/// upper limit of the comment length of a flag in bytes
+const MAX_FLAG_COMMENT: usize = 1000;
+
+#[derive(Deserialize)]
+struct FlagRequest {
+    reason: FlagReason,
+    comment: Option<String>,
+}
+
+/// reports an image, it is excluded from the matchmaking until an admin reviewed it
+#[post("/images/{id}/flag")]
+async fn flag_image(
+    id: web::Path<u32>,
+    flag: web::Json<FlagRequest>,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    let comment = flag
+        .comment
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    if comment.is_some_and(|c| c.len() > MAX_FLAG_COMMENT) {
+        return Err(error::ErrorBadRequest(format!(
+            "comment must not be longer than {MAX_FLAG_COMMENT} bytes"
+        )));
+    }
+    match collection.flag_player(*id, flag.reason, comment).await {
+        Ok(true) => Ok(HttpResponse::Created().finish()),
+        Ok(false) => Err(error::ErrorNotFound(format!("no image with id {id}"))),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+struct FlagsQuery {
+    /// only flags with this status, all flags if not given
+    status: Option<FlagStatus>,
+}
+
+#[get("/api/flags")]
+async fn list_flags(
+    query: web::Query<FlagsQuery>,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    match collection.flags(query.status).await {
+        Ok(flags) => Ok(HttpResponse::Ok().json(flags)),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+struct ReviewRequest {
+    /// "cleared" or "removed"
+    status: FlagStatus,
+}
+
+/// clears or removes a flagged image
+#[post("/api/players/{id}/review")]
+async fn review_player(
+    req: HttpRequest,
+    id: web::Path<u32>,
+    review: web::Json<ReviewRequest>,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    authorize(&req)?;
+    if review.status == FlagStatus::Open {
+        return Err(error::ErrorBadRequest(
+            "status must be \"cleared\" or \"removed\"",
+        ));
+    }
+    match collection.review_player(*id, review.status).await {
+        Ok(true) => Ok(HttpResponse::NoContent().finish()),
+        Ok(false) => Err(error::ErrorNotFound(format!("image {id} is not flagged"))),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
+    }
+}
+
+/// writes a snapshot of the database into the backup directory
+#[post("/admin/backup")]
+async fn write_backup(
+    req: HttpRequest,
+    collection: web::Data<ImageCollection>,
+) -> actix_web::Result<HttpResponse> {
+    authorize(&req)?;
+    if !collection.backups_enabled() {
+        return Err(error::ErrorNotFound(
+            "backups are disabled, start the server with --backup-dir",
+        ));
+    }
+    match collection.backup().await {
+        Ok(backup) => Ok(HttpResponse::Created().json(backup)),
+        Err(err) => Err(error::ErrorInternalServerError(err.to_string())),
     }
 }
 
@@ -126,24 +743,157 @@ async fn main() -> Result<()> {
 
     let args = Args::parse();
 
+    if args
+        .target_deviation
+        .is_some_and(|d| !d.is_finite() || d <= 0_f64)
+    {
+        anyhow::bail!("--target-deviation must be a positive number");
+    }
+    if args.backup_dir.is_some() && image_collection::is_postgres_url(&args.output) {
+        anyhow::bail!("--backup-dir only works with sqlite, back up PostgreSQL with pg_dump");
+    }
+    if !(0_f64..=1_f64).contains(&args.gold_rate) {
+        anyhow::bail!("--gold-rate must be between 0 and 1");
+    }
+    if !(0_f64..=1_f64).contains(&args.consistency_rate) {
+        anyhow::bail!("--consistency-rate must be between 0 and 1");
+    }
+    if args
+        .min_annotator_accuracy
+        .is_some_and(|a| !(a > 0.5 && a <= 1_f64))
+    {
+        anyhow::bail!("--min-annotator-accuracy must be above 0.5 and at most 1");
+    }
+    let gold_pairs = match &args.gold_pairs {
+        Some(path) => image_collection::read_gold_pairs(path)?,
+        None => Vec::new(),
+    };
+
     let options = ImageCollectionOptions {
         db_path: args.output,
         candidate_buffer: args.queue_buffer,
+        matchmaking: args.matchmaking,
+        stopping: StoppingCriterion {
+            max_deviation: args.target_deviation,
+            max_rank_error: args.target_rank_error,
+            max_games: args.max_games,
+        },
+        correct_position_bias: args.correct_position_bias,
+        response_time_weighting: args.response_time_weighting,
+        gold_pairs,
+        gold_rate: args.gold_rate,
+        quality_control: args
+            .min_annotator_accuracy
+            .map(|min_accuracy| QualityControl {
+                min_accuracy,
+                min_answers: args.min_gold_answers,
+                low_accuracy: args.low_accuracy,
+            }),
+        consistency_rate: args.consistency_rate,
+        sessions: SessionLimits {
+            max_matches: args.session_max_matches,
+            max_seconds: args.session_max_minutes.map(|m| m * 60),
+            break_seconds: args.session_break_minutes * 60,
+        },
+        read_connections: args.read_connections,
+        backup: args.backup_dir.map(|dir| BackupOptions {
+            dir: dir.into(),
+            keep: args.backup_keep,
+        }),
     };
     let img_col = ImageCollection::new(&options, &args.image_dir).await?;
+    if let Some(Command::Import { file }) = &args.command {
+        let matches = image_collection::read_matches(file)?;
+        let imported = img_col.import_matches(&matches).await;
+        img_col.close().await;
+        println!("Imported {} matches from {}.", imported?, file.display());
+        return Ok(());
+    }
+    let preview = PreviewSettings {
+        window: args.preview_window,
+        channels: args.preview_channels,
+        raw: args.raw_format,
+    };
+    if !matches!(preview.channels.len(), 0 | 1 | 3) {
+        anyhow::bail!("--preview-channels needs one or three channels");
+    }
+    let image_service = web::Data::new(images::ImageService::new(
+        &args.image_dir,
+        &args.cache_dir,
+        args.cache_max_mb.saturating_mul(1024 * 1024),
+        preview,
+    )?);
+
+    let bindings = web::Data::new(match &args.key_bindings {
+        Some(path) => KeyBindings::from_file(path)?,
+        None => KeyBindings::default(),
+    });
+    let settings = web::Data::new(PageSettings {
+        graded_outcomes: args.graded_outcomes,
+    });
+
+    if let Some(minutes) = args.backup_interval_minutes {
+        if !img_col.backups_enabled() {
+            anyhow::bail!("--backup-interval-minutes needs --backup-dir");
+        }
+        if minutes == 0 {
+            anyhow::bail!("--backup-interval-minutes must be at least 1");
+        }
+        let collection = img_col.clone();
+        actix_web::rt::spawn(async move {
+            let period = std::time::Duration::from_secs(minutes * 60);
+            let mut interval = actix_web::rt::time::interval_at(
+                actix_web::rt::time::Instant::now() + period,
+                period,
+            );
+            loop {
+                interval.tick().await;
+                if let Err(err) = collection.backup().await {
+                    tracing::error!("Error during the periodic backup {}", err);
+                }
+            }
+        });
+    }
+
+    if args.admin_token.is_none() {
+        tracing::warn!(
+            "No --admin-token given, the admin actions are open to everyone who can reach the server."
+        );
+    }
+    let admin_token = web::Data::new(AdminToken(args.admin_token));
 
     let addr = format!("[::]:{}", args.port);
-    let image_dir = args.image_dir;
     let img_col_closure = img_col.clone();
     let server = HttpServer::new(move || {
         App::new()
             .app_data(actix_web::web::Data::new(img_col_closure.clone()))
+            .app_data(image_service.clone())
+            .app_data(bindings.clone())
+            .app_data(settings.clone())
+            .app_data(admin_token.clone())
             .wrap(actix_web::middleware::Logger::new("%r - %s - %Dms"))
             .service(index)
+            .service(admin)
+            .service(leaderboard)
             .service(return_new_match)
             .service(on_new_score)
+            .service(campaign_status)
+            .service(campaign_stats)
+            .service(serve_key_bindings)
+            .service(page_settings)
+            .service(list_players)
+            .service(recent_matches)
+            .service(export_matches)
+            .service(recent_activity)
+            .service(list_annotators)
+            .service(set_annotator_weight)
+            .service(annotator_session)
+            .service(flag_image)
+            .service(list_flags)
+            .service(review_player)
+            .service(write_backup)
             .service(blank_image)
-            .service(actix_files::Files::new("/images", &image_dir).prefer_utf8(true))
+            .service(images::serve_image)
             .service(style)
     })
     .keep_alive(std::time::Duration::new(90, 0))
@@ -160,7 +910,10 @@ async fn main() -> Result<()> {
 mod tests {
     use super::*;
     use actix_web::{App, test, web};
-    use image_collection::MatchOutcome;
+    use image_collection::{Activity, CampaignStats, CampaignStatus, MatchOutcome, MatchRecord};
+
+    /// upper limit for waiting on the rating updates in the background
+    const BACKGROUND_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
     #[actix_web::test]
     async fn test_get_matches_returns_duel() {
@@ -182,6 +935,74 @@ mod tests {
         assert!(!duel.guest.is_empty());
     }
 
+    #[actix_web::test]
+    async fn test_get_matches_with_count_returns_batch() {
+        let img_col = ImageCollection::new_pre_configured(10).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(return_new_match),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/matches?count=3")
+            .to_request();
+        let duels: Vec<image_collection::Duel> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(duels.len(), 3);
+
+        for uri in ["/matches?count=0", "/matches?count=1000"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 400);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_get_matches_checks_consistency_of_annotator() {
+        let options = ImageCollectionOptions {
+            consistency_rate: 1_f64,
+            ..Default::default()
+        };
+        let img_col = ImageCollection::new_pre_configured_with(5, &options)
+            .await
+            .unwrap();
+        img_col
+            .insert_match(Match {
+                home_id: 1,
+                guest_id: 2,
+                won: MatchOutcome::HomeWin,
+                home_side: None,
+                response_ms: None,
+                annotator: Some("alice".to_string()),
+                session_id: None,
+            })
+            .await;
+        actix_web::rt::time::timeout(BACKGROUND_TIMEOUT, async {
+            while img_col.recent_matches(1).await.unwrap().is_empty() {
+                actix_web::rt::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the match was not stored");
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(return_new_match),
+        )
+        .await;
+
+        // alice only judged a single pair, which gets repeated
+        let req = test::TestRequest::get()
+            .uri("/matches?count=3&annotator=alice")
+            .to_request();
+        let duels: Vec<image_collection::Duel> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(duels.len(), 3);
+        for duel in duels {
+            assert_eq!(duel.home_id.min(duel.guest_id), 1);
+            assert_eq!(duel.home_id.max(duel.guest_id), 2);
+        }
+    }
+
     #[actix_web::test]
     async fn test_post_matches_accepts_valid_match_and_returns_duel() {
         let img_col = ImageCollection::new_pre_configured(5).await.unwrap();
@@ -197,10 +1018,14 @@ mod tests {
             home_id: 1,
             guest_id: 2,
             won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
         };
         let req = test::TestRequest::post()
             .uri("/matches")
-            .set_json(&m)
+            .set_json(m)
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert!(resp.status().is_success());
@@ -247,6 +1072,234 @@ mod tests {
         assert_eq!(resp.status(), 400);
     }
 
+    #[actix_web::test]
+    async fn test_post_matches_accepts_skip() {
+        let img_col = ImageCollection::new_pre_configured(5).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col.clone()))
+                .service(on_new_score),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/matches")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(r#"{"home_id": 1, "guest_id": 2, "won": "skip"}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let recent = img_col.recent_matches(1).await.unwrap();
+        assert_eq!(recent[0].won, MatchOutcome::Skip);
+    }
+
+    #[actix_web::test]
+    async fn test_get_annotators() {
+        let img_col = ImageCollection::new_pre_configured(3).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(on_new_score)
+                .service(list_annotators),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/matches")
+            .set_json(serde_json::json!({"home_id": 1, "guest_id": 2, "won": "skip", "annotator": "alice"}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let req = test::TestRequest::get().uri("/api/annotators").to_request();
+        let annotators: Vec<image_collection::AnnotatorStats> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(annotators.len(), 1);
+        assert_eq!(annotators[0].annotator, "alice");
+        assert_eq!(annotators[0].matches, 1);
+        assert_eq!(annotators[0].accuracy, None);
+    }
+
+    #[actix_web::test]
+    async fn test_set_annotator_weight() {
+        let img_col = ImageCollection::new_pre_configured(3).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(set_annotator_weight)
+                .service(list_annotators),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/annotators/alice/weight")
+            .set_json(serde_json::json!({"weight": 2.5}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+        let req = test::TestRequest::post()
+            .uri("/api/annotators/alice/weight")
+            .set_json(serde_json::json!({"weight": -1}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        let req = test::TestRequest::get().uri("/api/annotators").to_request();
+        let annotators: Vec<image_collection::AnnotatorStats> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(annotators.len(), 1);
+        assert_eq!(annotators[0].annotator, "alice");
+        assert_eq!(annotators[0].game_weight, 2.5);
+    }
+
+    #[actix_web::test]
+    async fn test_admin_actions_need_the_token() {
+        let img_col = ImageCollection::new_pre_configured(3).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .app_data(web::Data::new(AdminToken(Some("secret".to_string()))))
+                .service(set_annotator_weight)
+                .service(review_player)
+                .service(write_backup),
+        )
+        .await;
+
+        for authorization in [None, Some("Bearer wrong"), Some("secret")] {
+            for uri in [
+                "/api/annotators/alice/weight",
+                "/api/players/1/review",
+                "/admin/backup",
+            ] {
+                let mut req = test::TestRequest::post()
+                    .uri(uri)
+                    .set_json(serde_json::json!({"weight": 2, "status": "cleared"}));
+                if let Some(authorization) = authorization {
+                    req = req.insert_header(("Authorization", authorization));
+                }
+                let resp = test::call_service(&app, req.to_request()).await;
+                assert_eq!(resp.status(), 401);
+            }
+        }
+        let req = test::TestRequest::post()
+            .uri("/api/annotators/alice/weight")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"weight": 2}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+        // without --backup-dir
+        let req = test::TestRequest::post()
+            .uri("/admin/backup")
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_session_break() {
+        let options = ImageCollectionOptions {
+            sessions: SessionLimits {
+                max_matches: Some(1),
+                ..SessionLimits::default()
+            },
+            ..ImageCollectionOptions::default()
+        };
+        let img_col = ImageCollection::new_pre_configured_with(3, &options)
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(on_new_score)
+                .service(annotator_session),
+        )
+        .await;
+
+        let skip =
+            serde_json::json!({"home_id": 1, "guest_id": 2, "won": "skip", "annotator": "alice"});
+        let req = test::TestRequest::post()
+            .uri("/matches")
+            .set_json(&skip)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let req = test::TestRequest::post()
+            .uri("/matches")
+            .set_json(&skip)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 429);
+        assert!(resp.headers().contains_key("Retry-After"));
+        let session: image_collection::SessionStatus = test::read_body_json(resp).await;
+        assert_eq!(session.matches, 1);
+        assert!(session.break_seconds > 0);
+
+        let req = test::TestRequest::get()
+            .uri("/api/session?annotator=bob")
+            .to_request();
+        let session: image_collection::SessionStatus =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(session.session_id, None);
+        assert_eq!(session.remaining_matches, Some(1));
+    }
+
+    #[actix_web::test]
+    async fn test_flag_and_review_image() {
+        let img_col = ImageCollection::new_pre_configured(5).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(flag_image)
+                .service(list_flags)
+                .service(review_player),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/images/2/flag")
+            .set_json(serde_json::json!({"reason": "corrupt", "comment": " only noise "}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+        let req = test::TestRequest::post()
+            .uri("/images/99/flag")
+            .set_json(serde_json::json!({"reason": "corrupt"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+        let req = test::TestRequest::post()
+            .uri("/images/2/flag")
+            .set_json(serde_json::json!({"reason": "ugly"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        let req = test::TestRequest::get()
+            .uri("/api/flags?status=open")
+            .to_request();
+        let flags: Vec<image_collection::FlagRecord> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].player_id, 2);
+        assert_eq!(flags[0].comment.as_deref(), Some("only noise"));
+
+        let req = test::TestRequest::post()
+            .uri("/api/players/2/review")
+            .set_json(serde_json::json!({"status": "open"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        let req = test::TestRequest::post()
+            .uri("/api/players/2/review")
+            .set_json(serde_json::json!({"status": "cleared"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+        let req = test::TestRequest::post()
+            .uri("/api/players/2/review")
+            .set_json(serde_json::json!({"status": "removed"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+        let req = test::TestRequest::get()
+            .uri("/api/flags?status=open")
+            .to_request();
+        let flags: Vec<image_collection::FlagRecord> =
+            test::call_and_read_body_json(&app, req).await;
+        assert!(flags.is_empty());
+    }
+
     #[actix_web::test]
     async fn test_get_matches_no_players_returns_error() {
         let img_col = ImageCollection::new_pre_configured(0).await.unwrap();
@@ -261,4 +1314,333 @@ mod tests {
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), 400);
     }
+
+    #[actix_web::test]
+    async fn test_get_key_bindings() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(KeyBindings::default()))
+                .service(serve_key_bindings),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/keybindings")
+            .to_request();
+        let bindings: KeyBindings = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(bindings, KeyBindings::default());
+    }
+
+    #[actix_web::test]
+    async fn test_get_page_settings() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(PageSettings {
+                    graded_outcomes: true,
+                }))
+                .service(page_settings),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/api/settings").to_request();
+        let settings: PageSettings = test::call_and_read_body_json(&app, req).await;
+        assert!(settings.graded_outcomes);
+    }
+
+    #[actix_web::test]
+    async fn test_get_status_reports_progress() {
+        let options = ImageCollectionOptions {
+            stopping: StoppingCriterion {
+                max_games: Some(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let img_col = ImageCollection::new_pre_configured_with(5, &options)
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(campaign_status),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/status").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let status: CampaignStatus = test::read_body_json(resp).await;
+        assert!(!status.complete);
+        assert_eq!(status.players, 5);
+        assert_eq!(status.games, 0);
+        assert_eq!(status.progress, Some(0.0));
+        assert_eq!(status.criterion.max_games, Some(10));
+    }
+
+    #[actix_web::test]
+    async fn test_get_matches_complete_campaign_returns_410() {
+        let options = ImageCollectionOptions {
+            stopping: StoppingCriterion {
+                max_deviation: Some(400.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let img_col = ImageCollection::new_pre_configured_with(5, &options)
+            .await
+            .unwrap();
+        // the criterion is evaluated on the first inserted match
+        img_col
+            .insert_match(Match {
+                home_id: 1,
+                guest_id: 2,
+                won: MatchOutcome::Draw,
+                home_side: None,
+                response_ms: None,
+                annotator: None,
+                session_id: None,
+            })
+            .await;
+        actix_web::rt::time::timeout(BACKGROUND_TIMEOUT, async {
+            while !img_col.is_complete() {
+                actix_web::rt::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the campaign did not complete");
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(return_new_match),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/matches").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 410);
+
+        let status: CampaignStatus = test::read_body_json(resp).await;
+        assert!(status.complete);
+    }
+
+    #[actix_web::test]
+    async fn test_get_stats_returns_statistics() {
+        let img_col = ImageCollection::new_pre_configured(5).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(campaign_stats),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/stats?min_games=1&target_deviation=100")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let stats: CampaignStats = test::read_body_json(resp).await;
+        assert_eq!(stats.matches, 0);
+        assert_eq!(stats.min_games, 1);
+        assert_eq!(stats.players_below_min_games, 5);
+        assert_eq!(stats.target_deviation, Some(100.0));
+        assert!(stats.remaining_games.unwrap() > 0);
+
+        let req = test::TestRequest::get().uri("/stats").to_request();
+        let resp = test::call_service(&app, req).await;
+        let stats: CampaignStats = test::read_body_json(resp).await;
+        assert_eq!(stats.min_games, 5);
+        assert_eq!(stats.remaining_games, None);
+
+        let req = test::TestRequest::get()
+            .uri("/stats?target_deviation=0")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_admin_endpoints_return_json() {
+        let img_col = ImageCollection::new_pre_configured(3).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(on_new_score)
+                .service(list_players)
+                .service(recent_matches)
+                .service(recent_activity),
+        )
+        .await;
+
+        let m = Match {
+            home_id: 1,
+            guest_id: 2,
+            won: MatchOutcome::HomeWin,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
+        };
+        let req = test::TestRequest::post()
+            .uri("/matches")
+            .set_json(m)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let req = test::TestRequest::get().uri("/api/players").to_request();
+        let page: PlayerPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page.total, 3);
+        assert_eq!(page.players.len(), 3);
+
+        // the match gets inserted in the background
+        let matches: Vec<MatchRecord> = actix_web::rt::time::timeout(BACKGROUND_TIMEOUT, async {
+            loop {
+                actix_web::rt::time::sleep(std::time::Duration::from_millis(10)).await;
+                let req = test::TestRequest::get()
+                    .uri("/api/matches?limit=5")
+                    .to_request();
+                let matches: Vec<MatchRecord> = test::call_and_read_body_json(&app, req).await;
+                if !matches.is_empty() {
+                    break matches;
+                }
+            }
+        })
+        .await
+        .expect("the match was not inserted");
+        assert_eq!(matches[0].home_id, 1);
+        assert_eq!(matches[0].guest_id, 2);
+
+        let req = test::TestRequest::get().uri("/api/activity").to_request();
+        let activity: Vec<Activity> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(activity.iter().map(|a| a.matches).sum::<u64>(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_export_matches_as_csv() {
+        let img_col = ImageCollection::new_pre_configured(3).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(on_new_score)
+                .service(export_matches),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/matches")
+            .set_json(serde_json::json!({
+                "home_id": 2, "guest_id": 3, "won": 0.5, "home_side": "right", "response_ms": 1500,
+                "annotator": "alice"
+            }))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        // the match gets inserted in the background
+        let lines: Vec<String> = actix_web::rt::time::timeout(BACKGROUND_TIMEOUT, async {
+            loop {
+                actix_web::rt::time::sleep(std::time::Duration::from_millis(10)).await;
+                let req = test::TestRequest::get()
+                    .uri("/api/export/matches.csv")
+                    .to_request();
+                let body = test::call_and_read_body(&app, req).await;
+                let lines: Vec<String> = String::from_utf8(body.to_vec())
+                    .unwrap()
+                    .lines()
+                    .map(str::to_string)
+                    .collect();
+                if lines.len() >= 2 {
+                    break lines;
+                }
+            }
+        })
+        .await
+        .expect("the match was not inserted");
+        assert_eq!(
+            lines[0],
+            "id,home,guest,result,timestamp,home_side,response_ms,annotator,session_id"
+        );
+        let fields: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(fields[0], "1");
+        assert_eq!(fields[3], "0.5");
+        assert_eq!(fields[5..], ["right", "1500", "alice", "1"]);
+    }
+
+    #[actix_web::test]
+    async fn test_get_players_paginated_and_sorted() {
+        let img_col = ImageCollection::new_pre_configured(5).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(list_players),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/players?page=2&per_page=2&sort=deviation&order=asc")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["page"], 2);
+        assert_eq!(body["per_page"], 2);
+        assert_eq!(body["total"], 5);
+        let ids: Vec<u64> = body["players"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["id"].as_u64().unwrap())
+            .collect();
+        // all players share the same deviation, so they are ordered by id
+        assert_eq!(ids, vec![3, 4]);
+
+        for uri in [
+            "/api/players?page=0",
+            "/api/players?per_page=0",
+            "/api/players?per_page=501",
+            "/api/players?sort=name",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 400, "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_backup_endpoint() {
+        let img_col = ImageCollection::new_pre_configured(3).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(write_backup),
+        )
+        .await;
+        let req = test::TestRequest::post().uri("/admin/backup").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let dir = std::env::temp_dir().join(format!("regan-backup-{}", std::process::id()));
+        let options = ImageCollectionOptions {
+            backup: Some(BackupOptions {
+                dir: dir.clone(),
+                keep: 1,
+            }),
+            ..ImageCollectionOptions::default()
+        };
+        let img_col = ImageCollection::new_pre_configured_with(3, &options)
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(img_col))
+                .service(write_backup),
+        )
+        .await;
+        let req = test::TestRequest::post().uri("/admin/backup").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let backup: image_collection::Backup = test::read_body_json(resp).await;
+        assert!(backup.bytes > 0);
+        assert!(std::path::Path::new(&backup.path).starts_with(&dir));
+        assert!(std::path::Path::new(&backup.path).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
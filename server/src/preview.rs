@@ -0,0 +1,467 @@
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+use image::{DynamicImage, GrayImage, RgbImage};
+use tiff::ColorType;
+use tiff::decoder::{Decoder, DecodingResult};
+
+/// percentiles are estimated on at most this many samples per channel
+const MAX_PERCENTILE_SAMPLES: usize = 1 << 20;
+
+/// Maps the sample values of a channel to the displayable range
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Window {
+    /// contrast stretch between the given percentiles of each channel
+    Percentile { low: f32, high: f32 },
+    /// fixed sample values, shown as black and white
+    Fixed { min: f32, max: f32 },
+}
+
+impl FromStr for Window {
+    type Err = String;
+    /// "0.5%,99.5%" for percentiles or "100,4000" for fixed values
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err =
+            || format!("invalid window \"{s}\", expected e.g. \"0.5%,99.5%\" or \"100,4000\"");
+        let (low, high) = s.split_once(',').ok_or_else(err)?;
+        let (low, high) = (low.trim(), high.trim());
+        let percent = |v: &str| v.strip_suffix('%').map(|v| v.trim().parse::<f32>());
+        match (percent(low), percent(high)) {
+            (Some(Ok(low)), Some(Ok(high))) if (0.0..high).contains(&low) && high <= 100.0 => {
+                Ok(Window::Percentile { low, high })
+            }
+            (None, None) => match (low.parse::<f32>(), high.parse::<f32>()) {
+                (Ok(min), Ok(max)) if min < max => Ok(Window::Fixed { min, max }),
+                _ => Err(err()),
+            },
+            _ => Err(err()),
+        }
+    }
+}
+
+/// Type of a single sample of a raw file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SampleType {
+    U8,
+    U16Le,
+    U16Be,
+    F32Le,
+}
+
+impl SampleType {
+    fn size(&self) -> usize {
+        match self {
+            SampleType::U8 => 1,
+            SampleType::U16Le | SampleType::U16Be => 2,
+            SampleType::F32Le => 4,
+        }
+    }
+}
+
+/// Layout of headerless raw files with interleaved channels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RawFormat {
+    pub width: u32,
+    pub height: u32,
+    pub channels: usize,
+    pub sample: SampleType,
+}
+
+impl FromStr for RawFormat {
+    type Err = String;
+    /// "<width>x<height>x<channels>:<u8|u16le|u16be|f32le>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid raw format \"{s}\", expected e.g. \"2048x1536x1:u16le\"");
+        let (dims, sample) = s.split_once(':').ok_or_else(err)?;
+        let sample = match sample {
+            "u8" => SampleType::U8,
+            "u16le" => SampleType::U16Le,
+            "u16be" => SampleType::U16Be,
+            "f32le" => SampleType::F32Le,
+            _ => return Err(err()),
+        };
+        let dims: Vec<usize> = dims
+            .split('x')
+            .map(|d| d.parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| err())?;
+        match dims[..] {
+            [width, height, channels] if width > 0 && height > 0 && channels > 0 => Ok(RawFormat {
+                width: width as u32,
+                height: height as u32,
+                channels,
+                sample,
+            }),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// How previews of images, which browsers can not display, are rendered
+#[derive(Clone, Debug)]
+pub struct PreviewSettings {
+    pub window: Window,
+    /// one channel for grayscale or three for RGB.
+    /// Uses the first or the first three channels if empty.
+    pub channels: Vec<usize>,
+    /// ".raw" files are only supported with a given layout
+    pub raw: Option<RawFormat>,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        PreviewSettings {
+            window: Window::Percentile {
+                low: 0.5,
+                high: 99.5,
+            },
+            channels: Vec::new(),
+            raw: None,
+        }
+    }
+}
+
+/// All channels of an image as planes of float samples
+struct Stack {
+    width: u32,
+    height: u32,
+    channels: Vec<Vec<f32>>,
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+}
+
+impl PreviewSettings {
+    /// true, if the image needs a rendered preview to be shown in a browser
+    pub fn handles(&self, path: &Path) -> bool {
+        match extension(path).as_deref() {
+            Some("tif" | "tiff") => true,
+            Some("raw") => self.raw.is_some(),
+            _ => false,
+        }
+    }
+
+    /// changes whenever a preview would be rendered differently
+    pub fn fingerprint(&self) -> String {
+        format!("{self:?}")
+    }
+
+    pub fn render(&self, path: &Path) -> Result<DynamicImage> {
+        let stack = match (extension(path).as_deref(), self.raw) {
+            (Some("raw"), Some(raw)) => read_raw(path, &raw)?,
+            _ => read_tiff(path)?,
+        };
+        let selected: Vec<usize> = match (self.channels.len(), stack.channels.len()) {
+            (0, 0) => bail!("image has no channels"),
+            (0, 1 | 2) => vec![0],
+            (0, _) => vec![0, 1, 2],
+            _ => self.channels.clone(),
+        };
+        if selected.len() != 1 && selected.len() != 3 {
+            bail!("previews need one or three channels");
+        }
+        let mut planes = Vec::with_capacity(selected.len());
+        for &c in &selected {
+            let Some(channel) = stack.channels.get(c) else {
+                bail!(
+                    "channel {c} does not exist, the image has {} channels",
+                    stack.channels.len()
+                );
+            };
+            planes.push(apply_window(channel, self.window));
+        }
+
+        let (width, height) = (stack.width, stack.height);
+        if let [gray] = &planes[..] {
+            let img = GrayImage::from_raw(width, height, gray.clone())
+                .ok_or_else(|| anyhow::anyhow!("invalid image size"))?;
+            return Ok(DynamicImage::ImageLuma8(img));
+        }
+        let rgb: Vec<u8> = (0..planes[0].len())
+            .flat_map(|i| [planes[0][i], planes[1][i], planes[2][i]])
+            .collect();
+        let img = RgbImage::from_raw(width, height, rgb)
+            .ok_or_else(|| anyhow::anyhow!("invalid image size"))?;
+        Ok(DynamicImage::ImageRgb8(img))
+    }
+}
+
+fn samples_per_pixel(color: ColorType) -> Result<usize> {
+    Ok(match color {
+        ColorType::Gray(_) | ColorType::Palette(_) => 1,
+        ColorType::GrayA(_) => 2,
+        ColorType::RGB(_) | ColorType::YCbCr(_) => 3,
+        ColorType::RGBA(_) | ColorType::CMYK(_) => 4,
+        ColorType::CMYKA(_) => 5,
+        ColorType::Multiband { num_samples, .. } => num_samples as usize,
+        _ => bail!("unsupported tiff color type {color:?}"),
+    })
+}
+
+fn to_f32(result: DecodingResult) -> Vec<f32> {
+    match result {
+        DecodingResult::U8(v) => v.into_iter().map(f32::from).collect(),
+        DecodingResult::U16(v) => v.into_iter().map(f32::from).collect(),
+        DecodingResult::U32(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::U64(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::F16(v) => v.into_iter().map(|s| s.to_f32()).collect(),
+        DecodingResult::F32(v) => v,
+        DecodingResult::F64(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::I8(v) => v.into_iter().map(f32::from).collect(),
+        DecodingResult::I16(v) => v.into_iter().map(f32::from).collect(),
+        DecodingResult::I32(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::I64(v) => v.into_iter().map(|s| s as f32).collect(),
+    }
+}
+
+/// splits interleaved samples into one plane per channel
+fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    (0..channels)
+        .map(|c| samples.iter().skip(c).step_by(channels).copied().collect())
+        .collect()
+}
+
+/// Reads all pages of a tiff. Every sample of every page becomes a channel,
+/// so multi-band images and multi-page stacks are handled the same way.
+fn read_tiff(path: &Path) -> Result<Stack> {
+    let file = BufReader::new(std::fs::File::open(path)?);
+    let mut decoder = Decoder::new(file)?;
+    let mut stack: Option<Stack> = None;
+    loop {
+        let (width, height) = decoder.dimensions()?;
+        let samples = samples_per_pixel(decoder.colortype()?)?;
+        let channels = deinterleave(&to_f32(decoder.read_image()?), samples);
+        match stack.as_mut() {
+            None => {
+                stack = Some(Stack {
+                    width,
+                    height,
+                    channels,
+                })
+            }
+            Some(s) if (s.width, s.height) == (width, height) => s.channels.extend(channels),
+            Some(_) => bail!("all pages of a tiff stack need the same size"),
+        }
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image()?;
+    }
+    stack.ok_or_else(|| anyhow::anyhow!("tiff without images"))
+}
+
+fn read_raw(path: &Path, format: &RawFormat) -> Result<Stack> {
+    let bytes = std::fs::read(path)?;
+    let n_samples = format.width as usize * format.height as usize * format.channels;
+    if bytes.len() != n_samples * format.sample.size() {
+        bail!(
+            "raw file has {} bytes, but the raw format requires {}",
+            bytes.len(),
+            n_samples * format.sample.size()
+        );
+    }
+    let samples: Vec<f32> = match format.sample {
+        SampleType::U8 => bytes.iter().map(|&b| f32::from(b)).collect(),
+        SampleType::U16Le => bytes
+            .chunks_exact(2)
+            .map(|b| f32::from(u16::from_le_bytes([b[0], b[1]])))
+            .collect(),
+        SampleType::U16Be => bytes
+            .chunks_exact(2)
+            .map(|b| f32::from(u16::from_be_bytes([b[0], b[1]])))
+            .collect(),
+        SampleType::F32Le => bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    };
+    Ok(Stack {
+        width: format.width,
+        height: format.height,
+        channels: deinterleave(&samples, format.channels),
+    })
+}
+
+/// returns the value at the given percentile, ignoring NaNs
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0_f32;
+    }
+    let i = ((p / 100_f32) * (sorted.len() - 1) as f32).round() as usize;
+    sorted[i.min(sorted.len() - 1)]
+}
+
+fn apply_window(channel: &[f32], window: Window) -> Vec<u8> {
+    let (min, max) = match window {
+        Window::Fixed { min, max } => (min, max),
+        Window::Percentile { low, high } => {
+            let step = channel.len().div_ceil(MAX_PERCENTILE_SAMPLES).max(1);
+            let mut sorted: Vec<f32> = channel
+                .iter()
+                .step_by(step)
+                .copied()
+                .filter(|v| !v.is_nan())
+                .collect();
+            sorted.sort_by(f32::total_cmp);
+            (percentile(&sorted, low), percentile(&sorted, high))
+        }
+    };
+    let range = (max - min).max(f32::EPSILON);
+    channel
+        .iter()
+        .map(|&v| (((v - min) / range).clamp(0_f32, 1_f32) * 255_f32).round() as u8)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiff::encoder::{TiffEncoder, colortype};
+
+    fn temp_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("regan-preview-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_window_from_str() {
+        assert_eq!(
+            "0.5%,99.5%".parse::<Window>(),
+            Ok(Window::Percentile {
+                low: 0.5,
+                high: 99.5
+            })
+        );
+        assert_eq!(
+            "100, 4000".parse::<Window>(),
+            Ok(Window::Fixed {
+                min: 100.0,
+                max: 4000.0
+            })
+        );
+        assert!("4000,100".parse::<Window>().is_err());
+        assert!("1%,200%".parse::<Window>().is_err());
+        assert!("1%,100".parse::<Window>().is_err());
+        assert!("100".parse::<Window>().is_err());
+    }
+
+    #[test]
+    fn test_raw_format_from_str() {
+        assert_eq!(
+            "2048x1536x1:u16le".parse::<RawFormat>(),
+            Ok(RawFormat {
+                width: 2048,
+                height: 1536,
+                channels: 1,
+                sample: SampleType::U16Le
+            })
+        );
+        assert!("2048x1536:u16le".parse::<RawFormat>().is_err());
+        assert!("2048x1536x1:u12".parse::<RawFormat>().is_err());
+        assert!("0x1536x1:u8".parse::<RawFormat>().is_err());
+    }
+
+    #[test]
+    fn test_apply_window() {
+        let channel = [0.0, 1000.0, 2000.0, 3000.0, 4000.0];
+        let fixed = apply_window(
+            &channel,
+            Window::Fixed {
+                min: 1000.0,
+                max: 3000.0,
+            },
+        );
+        assert_eq!(fixed, vec![0, 0, 128, 255, 255]);
+        let stretched = apply_window(
+            &channel,
+            Window::Percentile {
+                low: 0.0,
+                high: 100.0,
+            },
+        );
+        assert_eq!(stretched, vec![0, 64, 128, 191, 255]);
+    }
+
+    #[test]
+    fn test_render_16_bit_tiff() {
+        let path = temp_file("gray16.tif");
+        let data: Vec<u16> = (0..64 * 32).map(|i| 1000 + i as u16).collect();
+        let mut encoder = TiffEncoder::new(std::fs::File::create(&path).unwrap()).unwrap();
+        encoder
+            .write_image::<colortype::Gray16>(64, 32, &data)
+            .unwrap();
+
+        let settings = PreviewSettings::default();
+        assert!(settings.handles(&path));
+        let img = settings.render(&path).unwrap().to_luma8();
+        assert_eq!(img.dimensions(), (64, 32));
+        // the contrast stretch uses the full range
+        assert_eq!(img.get_pixel(0, 0).0[0], 0);
+        assert_eq!(img.get_pixel(63, 31).0[0], 255);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_render_multi_page_stack_as_rgb() {
+        let path = temp_file("stack.tif");
+        let mut encoder = TiffEncoder::new(std::fs::File::create(&path).unwrap()).unwrap();
+        for page in 0..4_u16 {
+            let data = vec![page * 100; 8 * 8];
+            encoder
+                .write_image::<colortype::Gray16>(8, 8, &data)
+                .unwrap();
+        }
+
+        let settings = PreviewSettings {
+            window: Window::Fixed {
+                min: 0.0,
+                max: 300.0,
+            },
+            channels: vec![3, 2, 0],
+            raw: None,
+        };
+        let img = settings.render(&path).unwrap().to_rgb8();
+        assert_eq!(img.get_pixel(4, 4).0, [255, 170, 0]);
+
+        let settings = PreviewSettings {
+            channels: vec![4],
+            ..settings
+        };
+        assert!(settings.render(&path).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_render_raw() {
+        let path = temp_file("frame.raw");
+        let data: Vec<u8> = [0_u16, 500, 1000, 1500]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        std::fs::write(&path, data).unwrap();
+
+        assert!(!PreviewSettings::default().handles(&path));
+        let settings = PreviewSettings {
+            window: Window::Fixed {
+                min: 0.0,
+                max: 1000.0,
+            },
+            channels: Vec::new(),
+            raw: Some("2x2x1:u16le".parse().unwrap()),
+        };
+        assert!(settings.handles(&path));
+        let img = settings.render(&path).unwrap().to_luma8();
+        assert_eq!(img.into_raw(), vec![0, 128, 255, 255]);
+
+        let settings = PreviewSettings {
+            raw: Some("3x2x1:u16le".parse().unwrap()),
+            ..settings
+        };
+        assert!(settings.render(&path).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,6 +1,8 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use clap::Parser;
-use image_collection::{ImageCollection, Match, MatchOutcome};
+use image_collection::{
+    ImageCollectionOptions, Match, MatchOutcome, Matchmaking, MemoryStorage, RatingModel, Storage,
+};
 use rand_distr::{Distribution, Normal};
 
 #[derive(Parser, Debug)]
@@ -25,6 +27,12 @@ struct Args {
     #[clap(long, default_value_t = 0.0_f64)]
     std_dev: f64,
 
+    /// The matchmaking strategy which gets simulated.
+    /// "random" picks the most uncertain images and a random opponent in their confidence interval,
+    /// "swiss" plays deterministic Swiss-system rounds, so every image gets the same number of games
+    #[clap(long, default_value_t = Matchmaking::Random)]
+    matchmaking: Matchmaking,
+
     /// Prints timings for program runtime
     #[clap(long, default_value_t = false, value_parser)]
     print_timing: bool,
@@ -34,14 +42,26 @@ struct Args {
     no_csv: bool,
 }
 
-async fn run_simulation(samples: usize, games: usize, std_dev: f64) -> Result<ImageCollection> {
-    let collection = ImageCollection::new_pre_configured(samples as u32).await?;
+async fn run_simulation(
+    samples: usize,
+    games: usize,
+    std_dev: f64,
+    matchmaking: Matchmaking,
+) -> Result<MemoryStorage> {
+    let storage = MemoryStorage::new_pre_configured(samples as u32);
+    let model = RatingModel::default();
+    let candidate_buffer = ImageCollectionOptions::default().candidate_buffer;
+    let mut duels = std::collections::VecDeque::new();
 
     let distribution = Normal::new(0_f64, std_dev)?;
     let mut rng = rand::rng();
 
     for _ in 0..games {
-        let new_duel = collection.new_duel().await.unwrap();
+        // like the candidate queue of the server, the duels are computed in batches
+        if duels.is_empty() {
+            duels.extend(storage.candidates(candidate_buffer, matchmaking).await?);
+        }
+        let new_duel = duels.pop_front().ok_or(anyhow!("No candidates found"))?;
         let home_value = new_duel.home.parse::<u32>().unwrap();
         let guest_value = new_duel.guest.parse::<u32>().unwrap();
         let home_id = new_duel.home_id;
@@ -57,10 +77,14 @@ async fn run_simulation(samples: usize, games: usize, std_dev: f64) -> Result<Im
             home_id,
             guest_id,
             won,
+            home_side: None,
+            response_ms: None,
+            annotator: None,
+            session_id: None,
         };
-        collection.insert_match(m).await;
+        storage.update_rating(&m, &model).await?;
     }
-    Ok(collection)
+    Ok(storage)
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -72,14 +96,15 @@ async fn main() -> Result<()> {
     let args = Args::parse();
 
     let start = std::time::Instant::now();
-    let collection = run_simulation(args.samples, args.games, args.std_dev).await?;
+    let storage = run_simulation(args.samples, args.games, args.std_dev, args.matchmaking).await?;
 
     if !args.no_csv {
-        collection.print_csv().await?;
+        image_collection::print_csv(&storage.players().await?);
     }
 
     if args.print_timing {
         let runs_per_sec = args.games as f64 / start.elapsed().as_secs_f64();
+        println!("matchmaking: {}", args.matchmaking);
         println!("runs per sec: {runs_per_sec}");
     }
 
@@ -97,8 +122,19 @@ mod simulation {
     #[tokio::test]
     async fn regression() {
         // tests if the implemented strategy can help us to keep our MSRE
-        let collection = run_simulation(500, 5000, 50_f64).await.unwrap();
-        let msre = collection.msre().await.unwrap();
+        let storage = run_simulation(500, 5000, 50_f64, Matchmaking::Random)
+            .await
+            .unwrap();
+        let msre = image_collection::msre(&storage.players().await.unwrap());
+        assert!(msre < 25.5, "msre: {msre}");
+    }
+
+    #[tokio::test]
+    async fn regression_swiss() {
+        let storage = run_simulation(500, 5000, 50_f64, Matchmaking::Swiss)
+            .await
+            .unwrap();
+        let msre = image_collection::msre(&storage.players().await.unwrap());
         assert!(msre < 25.5, "msre: {msre}");
     }
 }